@@ -0,0 +1,437 @@
+use crate::{settings::Settings, states::GameState, GameplaySet};
+use bevy::{
+    ecs::system::SystemParam,
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_common_assets::ron::RonAssetPlugin;
+use smallvec::{smallvec, SmallVec};
+
+const BINDINGS_PATH: &str = "input.bindings.ron";
+
+/// Loads key bindings, keeps the ActionBindings resource up to date with the bindings file,
+/// and reads the devices into the ActionState each update, and for each gameplay tick
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<ActionBindings>::new(&["bindings.ron"]))
+            .init_resource::<ActionBindings>()
+            .init_resource::<ActionState>()
+            .init_resource::<TickInput>()
+            .add_systems(Startup, load_bindings)
+            .add_systems(PreUpdate, read_actions.after(InputSystem))
+            .add_systems(Update, apply_loaded_bindings)
+            .add_systems(FixedUpdate, begin_tick.before(GameplaySet::Input))
+            .add_systems(FixedUpdate, end_tick.after(GameplaySet::Rules))
+            // nothing pressed in a menu carries over into the game
+            .add_systems(OnEnter(GameState::Playing), clear_tick_input);
+    }
+}
+
+/// the character received when backspace is typed
+pub const BACKSPACE: char = '\u{8}';
+
+/// Things the player can do, which are bound to keys and gamepad buttons
+#[derive(serde::Deserialize, serde::Serialize, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Jump,
+    Pause,
+    Confirm,
+    Back,
+    NavigateNext,
+    NavigatePrevious,
+    SwitchControls,
+    ToggleFps,
+    /// change the letter being entered to the next one, for typing without a keyboard
+    NextLetter,
+    PreviousLetter,
+    /// start entering another letter
+    AddLetter,
+    DeleteLetter,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Jump,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+        Action::NavigateNext,
+        Action::NavigatePrevious,
+        Action::SwitchControls,
+        Action::ToggleFps,
+        Action::NextLetter,
+        Action::PreviousLetter,
+        Action::AddLetter,
+        Action::DeleteLetter,
+    ];
+}
+
+/// A key or gamepad button that can trigger an action
+#[derive(serde::Deserialize, serde::Serialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    /// this button on any connected gamepad
+    Button(GamepadButtonType),
+}
+
+/// How analog sticks are read
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct StickSettings {
+    /// stick deflection below this is ignored
+    pub deadzone: f32,
+    /// scales the left stick's movement
+    pub move_sensitivity: f32,
+    /// how fast the right stick turns the view, in radians per second
+    pub look_sensitivity: f32,
+    pub invert_look_y: bool,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        StickSettings {
+            deadzone: 0.15,
+            move_sensitivity: 1.0,
+            look_sensitivity: 2.5,
+            invert_look_y: false,
+        }
+    }
+}
+
+/// The keys and buttons bound to each action
+///
+/// starts out with the default bindings, is replaced when the bindings file loads,
+/// and may be changed at runtime
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    bevy::asset::Asset,
+    bevy::reflect::TypePath,
+    Resource,
+    Clone,
+    Debug,
+)]
+pub struct ActionBindings {
+    bindings: HashMap<Action, SmallVec<[Binding; 4]>>,
+    #[serde(default)]
+    pub sticks: StickSettings,
+}
+
+impl Default for ActionBindings {
+    fn default() -> Self {
+        use Action as A;
+        use Binding::{Button, Key};
+        use GamepadButtonType as G;
+        use KeyCode as K;
+        ActionBindings {
+            bindings: HashMap::from([
+                (
+                    A::MoveForward,
+                    smallvec![Key(K::W), Key(K::Up), Button(G::DPadUp)],
+                ),
+                (
+                    A::MoveBack,
+                    smallvec![Key(K::S), Key(K::Down), Button(G::DPadDown)],
+                ),
+                (A::StrafeLeft, smallvec![Key(K::A)]),
+                (A::StrafeRight, smallvec![Key(K::D)]),
+                (A::TurnLeft, smallvec![Key(K::Left), Button(G::DPadLeft)]),
+                (A::TurnRight, smallvec![Key(K::Right), Button(G::DPadRight)]),
+                (A::Jump, smallvec![Key(K::Space), Button(G::South)]),
+                (A::Pause, smallvec![Key(K::Escape), Button(G::Start)]),
+                (
+                    A::Confirm,
+                    smallvec![Key(K::Space), Key(K::Return), Button(G::South)],
+                ),
+                (A::Back, smallvec![Key(K::Back), Button(G::East)]),
+                (
+                    A::NavigateNext,
+                    smallvec![Key(K::Tab), Button(G::DPadDown), Button(G::DPadRight)],
+                ),
+                (
+                    A::NavigatePrevious,
+                    smallvec![Button(G::DPadUp), Button(G::DPadLeft)],
+                ),
+                (A::SwitchControls, smallvec![Key(K::C), Button(G::North)]),
+                (A::ToggleFps, smallvec![Key(K::F12), Button(G::Select)]),
+                // name and seed entry has its own bindings, as it is read while menus are open
+                (
+                    A::NextLetter,
+                    smallvec![Key(K::PageUp), Button(G::RightTrigger)],
+                ),
+                (
+                    A::PreviousLetter,
+                    smallvec![Key(K::PageDown), Button(G::LeftTrigger)],
+                ),
+                (
+                    A::AddLetter,
+                    smallvec![Key(K::Insert), Button(G::RightTrigger2)],
+                ),
+                (A::DeleteLetter, smallvec![Key(K::Delete), Button(G::West)]),
+            ]),
+            sticks: StickSettings::default(),
+        }
+    }
+}
+
+impl ActionBindings {
+    /// the keys and buttons bound to an action
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .get(&action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+    /// the keys bound to an action
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = KeyCode> + '_ {
+        self.bindings(action).iter().filter_map(|b| match b {
+            Binding::Key(key) => Some(*key),
+            Binding::Button(_) => None,
+        })
+    }
+    /// the gamepad buttons bound to an action
+    pub fn buttons(&self, action: Action) -> impl Iterator<Item = GamepadButtonType> + '_ {
+        self.bindings(action).iter().filter_map(|b| match b {
+            Binding::Key(_) => None,
+            Binding::Button(button) => Some(*button),
+        })
+    }
+    /// add a key or button to an action, in addition to any existing bindings
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    /// remove a key or button from an action
+    pub fn unbind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+    /// replace all keys and buttons bound to an action
+    pub fn rebind(&mut self, action: Action, bindings: impl IntoIterator<Item = Binding>) {
+        self.bindings.insert(action, bindings.into_iter().collect());
+    }
+}
+
+/// Handle to the bindings file, kept so it stays loaded and can be hot reloaded
+#[derive(Resource)]
+pub struct BindingsHandle(pub Handle<ActionBindings>);
+
+fn load_bindings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(BindingsHandle(asset_server.load(BINDINGS_PATH)));
+}
+
+/// replace the current bindings whenever the bindings file is loaded or changed
+fn apply_loaded_bindings(
+    mut events: EventReader<AssetEvent<ActionBindings>>,
+    handle: Option<Res<BindingsHandle>>,
+    assets: Res<Assets<ActionBindings>>,
+    mut bindings: ResMut<ActionBindings>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in events.read() {
+        if event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0) {
+            if let Some(loaded) = assets.get(&handle.0) {
+                *bindings = loaded.clone();
+            }
+        }
+    }
+}
+
+/// Reads the keyboard, mouse and gamepads in terms of actions
+#[derive(SystemParam)]
+struct Devices<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    mouse: EventReader<'w, 's, MouseMotion>,
+    bindings: Res<'w, ActionBindings>,
+    settings: Res<'w, Settings>,
+}
+
+impl<'w, 's> Devices<'w, 's> {
+    /// the given button types, on every connected gamepad
+    fn gamepad_buttons(&self, action: Action) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.gamepads.iter().flat_map(move |gamepad| {
+            self.bindings
+                .buttons(action)
+                .map(move |button| GamepadButton::new(gamepad, button))
+        })
+    }
+    /// any key or button bound to this action is held down
+    fn pressed(&self, action: Action) -> bool {
+        self.keys.any_pressed(self.bindings.keys(action))
+            || self.buttons.any_pressed(self.gamepad_buttons(action))
+    }
+    /// any key or button bound to this action was pressed this frame
+    fn just_pressed(&self, action: Action) -> bool {
+        self.keys.any_just_pressed(self.bindings.keys(action))
+            || self.buttons.any_just_pressed(self.gamepad_buttons(action))
+    }
+    /// combined deflection of one stick across all gamepads, with the deadzone removed
+    fn stick(&self, x_axis: GamepadAxisType, y_axis: GamepadAxisType) -> Vec2 {
+        let raw: Vec2 = self
+            .gamepads
+            .iter()
+            .map(|gamepad| {
+                Vec2::new(
+                    self.axes
+                        .get(GamepadAxis::new(gamepad, x_axis))
+                        .unwrap_or(0.0),
+                    self.axes
+                        .get(GamepadAxis::new(gamepad, y_axis))
+                        .unwrap_or(0.0),
+                )
+            })
+            .sum();
+        let deadzone = self.bindings.sticks.deadzone.clamp(0.0, 0.99);
+        let length = raw.length().min(1.0);
+        if length <= deadzone {
+            return Vec2::ZERO;
+        }
+        // rescale so movement starts from zero at the edge of the deadzone
+        raw.normalize() * (length - deadzone) / (1.0 - deadzone)
+    }
+    /// left stick movement, x to the right and y forward, scaled by the move sensitivity
+    fn move_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY)
+            * self.bindings.sticks.move_sensitivity
+    }
+    /// right stick look rate in radians per second, x to the right and y up
+    fn look_stick(&self) -> Vec2 {
+        let sticks = &self.bindings.sticks;
+        let look = self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY)
+            * sticks.look_sensitivity;
+        if sticks.invert_look_y {
+            Vec2::new(look.x, -look.y)
+        } else {
+            look
+        }
+    }
+}
+
+/// The actions held and pressed, and the sticks and mouse, as the systems running now see them
+///
+/// this frame's input during an update, and this tick's input during a gameplay tick,
+/// which is replaced by the recorded input while a replay plays
+#[derive(serde::Deserialize, serde::Serialize, Resource, Default, Clone, PartialEq, Debug)]
+pub struct ActionState {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<Action>,
+    /// pressed this tick, and not the tick before
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_pressed: Vec<Action>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub move_stick: Vec2,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub look_stick: Vec2,
+    /// mouse movement this tick, scaled by the mouse sensitivity
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub mouse_look: Vec2,
+}
+
+fn is_zero(v: &Vec2) -> bool {
+    *v == Vec2::ZERO
+}
+
+/// The input for the next gameplay tick, gathered over the frames since the last tick,
+/// so that presses and mouse movement are neither missed nor repeated however many ticks
+/// a frame runs
+#[derive(Resource, Default)]
+pub struct TickInput(pub ActionState);
+
+/// read the devices into the action state, and add them to the input for the next tick
+fn read_actions(
+    mut devices: Devices,
+    mut state: ResMut<ActionState>,
+    mut tick: ResMut<TickInput>,
+) {
+    let mouse_motion = devices.mouse.read().fold(Vec2::ZERO, |a, d| a + d.delta);
+    *state = ActionState {
+        pressed: Action::ALL
+            .into_iter()
+            .filter(|action| devices.pressed(*action))
+            .collect(),
+        just_pressed: Action::ALL
+            .into_iter()
+            .filter(|action| devices.just_pressed(*action))
+            .collect(),
+        move_stick: devices.move_stick(),
+        look_stick: devices.look_stick(),
+        mouse_look: mouse_motion * devices.settings.mouse_sensitivity,
+    };
+    let tick = &mut tick.0;
+    for action in state.just_pressed.iter() {
+        if !tick.just_pressed.contains(action) {
+            tick.just_pressed.push(*action);
+        }
+    }
+    tick.pressed = state.pressed.clone();
+    tick.move_stick = state.move_stick;
+    tick.look_stick = state.look_stick;
+    tick.mouse_look += state.mouse_look;
+}
+
+/// give the tick its input, keeping this frame's input aside until the tick is over
+fn begin_tick(mut state: ResMut<ActionState>, mut tick: ResMut<TickInput>) {
+    std::mem::swap(&mut *state, &mut tick.0);
+}
+
+/// put this frame's input back, and use up the tick's presses and mouse movement
+fn end_tick(mut state: ResMut<ActionState>, mut tick: ResMut<TickInput>) {
+    std::mem::swap(&mut *state, &mut tick.0);
+    tick.0.just_pressed.clear();
+    tick.0.mouse_look = Vec2::ZERO;
+}
+
+fn clear_tick_input(mut tick: ResMut<TickInput>) {
+    tick.0 = ActionState::default();
+}
+
+/// Reads input in terms of actions instead of keys and buttons
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    state: Res<'w, ActionState>,
+}
+
+impl<'w> Actions<'w> {
+    /// any key or button bound to this action is held down
+    pub fn pressed(&self, action: Action) -> bool {
+        self.state.pressed.contains(&action)
+    }
+    /// any key or button bound to this action was pressed this frame, or since the last tick
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.state.just_pressed.contains(&action)
+    }
+    /// left stick movement, x to the right and y forward, scaled by the move sensitivity
+    pub fn move_stick(&self) -> Vec2 {
+        self.state.move_stick
+    }
+    /// right stick look rate in radians per second, x to the right and y up
+    pub fn look_stick(&self) -> Vec2 {
+        self.state.look_stick
+    }
+    /// mouse movement this frame, or since the last tick, scaled by the mouse sensitivity
+    pub fn mouse_look(&self) -> Vec2 {
+        self.state.mouse_look
+    }
+}
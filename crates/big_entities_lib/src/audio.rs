@@ -0,0 +1,221 @@
+use crate::{
+    pawn::PlayerView, pickup::Despawn, settings::Settings, states::GameState, GameplaySet,
+};
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+
+const MUSIC_PATH: &str = "audio/flying.ogg";
+const COLLECT_PATH: &str = "audio/collect.wav";
+const EXPIRE_PATH: &str = "audio/expire.wav";
+const FOOTSTEP_PATH: &str = "audio/footstep.wav";
+const HOVER_PATH: &str = "audio/hover.wav";
+const PRESS_PATH: &str = "audio/press.wav";
+const HUM_PATH: &str = "audio/hum.wav";
+/// sound emitters further than this from the player's view can't be heard
+const HEARING_DISTANCE: f32 = 15.0;
+
+/// Plays music, and the sound effects asked for with PlaySfx events
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(AudioPlugin)
+            .add_audio_channel::<MusicChannel>()
+            .add_audio_channel::<SfxChannel>()
+            .add_audio_channel::<EmitterChannel>()
+            .insert_resource(SpacialAudio {
+                max_distance: HEARING_DISTANCE,
+            })
+            .add_event::<PlaySfx>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(
+                Update,
+                (apply_volumes, play_sfx, start_emitters, attach_receiver),
+            )
+            .add_systems(FixedUpdate, stop_emitters.before(GameplaySet::Pickups))
+            .add_systems(OnEnter(GameState::Playing), resume_emitters)
+            .add_systems(OnExit(GameState::Playing), pause_emitters)
+            .add_systems(OnEnter(GameState::GameOver), stop_all_emitters)
+            .add_systems(OnEnter(GameState::MainMenu), stop_all_emitters);
+    }
+}
+
+/// The audio channel that music plays on
+#[derive(Resource)]
+pub struct MusicChannel;
+
+/// The audio channel that sound effects play on
+#[derive(Resource)]
+pub struct SfxChannel;
+
+/// The audio channel that the looping sounds of emitters play on
+///
+/// kept apart from the sound effects channel, as spatial audio sets the volume of each
+/// of its sounds, which the channel volume would overwrite
+#[derive(Resource)]
+pub struct EmitterChannel;
+
+/// A sound effect, for gameplay and ui to ask for without knowing its file
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Sfx {
+    /// a pickup was collected
+    Collect,
+    /// a pickup ran out of time
+    Expire,
+    Footstep,
+    /// a button was hovered or focused
+    Hover,
+    /// a button was pressed
+    Press,
+    /// a pickup calling out to be found
+    Hum,
+}
+
+/// Ask for a sound effect to be played
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlaySfx(pub Sfx);
+
+/// The loaded sound effects, and the music
+#[derive(Resource)]
+pub struct SoundEffects {
+    music: Handle<AudioSource>,
+    collect: Handle<AudioSource>,
+    expire: Handle<AudioSource>,
+    footstep: Handle<AudioSource>,
+    hover: Handle<AudioSource>,
+    press: Handle<AudioSource>,
+    hum: Handle<AudioSource>,
+}
+
+impl SoundEffects {
+    pub fn get(&self, sfx: Sfx) -> Handle<AudioSource> {
+        match sfx {
+            Sfx::Collect => self.collect.clone(),
+            Sfx::Expire => self.expire.clone(),
+            Sfx::Footstep => self.footstep.clone(),
+            Sfx::Hover => self.hover.clone(),
+            Sfx::Press => self.press.clone(),
+            Sfx::Hum => self.hum.clone(),
+        }
+    }
+    /// every sound, for the loading screen to wait for
+    pub fn handles(&self) -> [UntypedHandle; 7] {
+        [
+            &self.music,
+            &self.collect,
+            &self.expire,
+            &self.footstep,
+            &self.hover,
+            &self.press,
+            &self.hum,
+        ]
+        .map(|handle| handle.clone().untyped())
+    }
+}
+
+/// load the sounds, and start the music playing once it has loaded
+fn load_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    music: Res<AudioChannel<MusicChannel>>,
+) {
+    let sounds = SoundEffects {
+        music: asset_server.load(MUSIC_PATH),
+        collect: asset_server.load(COLLECT_PATH),
+        expire: asset_server.load(EXPIRE_PATH),
+        footstep: asset_server.load(FOOTSTEP_PATH),
+        hover: asset_server.load(HOVER_PATH),
+        press: asset_server.load(PRESS_PATH),
+        hum: asset_server.load(HUM_PATH),
+    };
+    music.play(sounds.music.clone()).looped();
+    commands.insert_resource(sounds);
+}
+
+/// keep the channel volumes at the volumes from the settings
+fn apply_volumes(
+    settings: Res<Settings>,
+    music: Res<AudioChannel<MusicChannel>>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+) {
+    if settings.is_changed() {
+        music.set_volume(f64::from(settings.music_volume));
+        sfx.set_volume(f64::from(settings.sfx_volume));
+    }
+}
+
+/// play the sound effects asked for this frame
+fn play_sfx(
+    mut events: EventReader<PlaySfx>,
+    sounds: Res<SoundEffects>,
+    sfx: Res<AudioChannel<SfxChannel>>,
+) {
+    for PlaySfx(effect) in events.read() {
+        sfx.play(sounds.get(*effect));
+    }
+}
+
+/// Loops a sound that pans and fades with the entity's position relative to the player's view
+#[derive(Component)]
+pub struct SoundEmitter(pub Sfx);
+
+/// start looping the sound of new emitters, for spatial audio to pan and fade
+fn start_emitters(
+    mut commands: Commands,
+    sounds: Res<SoundEffects>,
+    channel: Res<AudioChannel<EmitterChannel>>,
+    emitters: Query<(Entity, &SoundEmitter), Added<SoundEmitter>>,
+) {
+    for (entity, SoundEmitter(effect)) in emitters.iter() {
+        let instance = channel
+            .play(sounds.get(*effect))
+            .looped()
+            .with_volume(0.0)
+            .handle();
+        commands.entity(entity).insert(AudioEmitter {
+            instances: vec![instance],
+        });
+    }
+}
+
+/// stop the sounds of emitters that are about to be despawned,
+/// checked each tick since they are despawned the tick after being marked
+fn stop_emitters(
+    emitters: Query<&AudioEmitter, Added<Despawn>>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    for emitter in emitters.iter() {
+        for handle in emitter.instances.iter() {
+            if let Some(instance) = audio_instances.get_mut(handle) {
+                instance.stop(AudioTween::default());
+            }
+        }
+    }
+}
+
+/// hear the player's view
+fn attach_receiver(mut commands: Commands, views: Query<Entity, Added<PlayerView>>) {
+    for entity in views.iter() {
+        commands.entity(entity).insert(AudioReceiver);
+    }
+}
+
+/// emitters are only heard while the game is played
+fn pause_emitters(channel: Res<AudioChannel<EmitterChannel>>) {
+    channel.pause();
+}
+
+fn resume_emitters(channel: Res<AudioChannel<EmitterChannel>>) {
+    channel.resume();
+}
+
+/// the emitters of a game that has ended are despawned with it
+fn stop_all_emitters(channel: Res<AudioChannel<EmitterChannel>>) {
+    channel.stop();
+}
+
+/// play the sound at this asset path once, on the sound effects channel
+pub fn play_sound(world: &mut World, path: &str) {
+    let sound = world.resource::<AssetServer>().load(path.to_string());
+    world.resource::<AudioChannel<SfxChannel>>().play(sound);
+}
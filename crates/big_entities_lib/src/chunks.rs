@@ -1,42 +1,191 @@
-use bevy::prelude::*;
+use crate::{
+    pawn::{control_mode::ControlMode, Player},
+    rng::GameRng,
+    states::Scope,
+    Game, GameplaySet,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+use std::{collections::BTreeMap, ops::Range};
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkMap>()
+            .add_systems(FixedUpdate, stream_chunks.in_set(GameplaySet::Rules));
+    }
+}
 
 /// The position of this entity on its respective gameboard
-#[derive(Component, Eq, PartialEq, Clone, Copy, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Component, Eq, PartialEq, Clone, Copy, Debug)]
 pub struct BoardPosition {
     pub x: usize,
     pub y: usize,
 }
 
+/// World layout loaded from a `.world.ron` file
+///
+/// `heights` and `tiles` are optional grids of `height` rows with `width` entries each,
+/// indexed as `grid[y][x]`. leaving a grid out (or empty) falls back to defaults
 #[derive(serde::Deserialize, bevy::asset::Asset, bevy::reflect::TypePath)]
+#[serde(try_from = "WorldConfigurationDef")]
 pub struct WorldConfiguration {
     pub width: usize,
     pub height: usize,
+    /// fixed seed for this world, otherwise one is chosen at startup
+    pub seed: Option<u64>,
+    /// how the player controls their pawn when the game starts
+    pub control_mode: ControlMode,
+    heights: Option<Vec<f32>>,
+    tiles: Option<Vec<TileKind>>,
+}
+
+/// WorldConfiguration as written in the ron file, before the grids are validated
+#[derive(serde::Deserialize)]
+struct WorldConfigurationDef {
+    width: usize,
+    height: usize,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    control_mode: ControlMode,
+    #[serde(default)]
+    heights: Vec<Vec<f32>>,
+    #[serde(default)]
+    tiles: Vec<Vec<TileKind>>,
+}
+
+impl TryFrom<WorldConfigurationDef> for WorldConfiguration {
+    type Error = String;
+    fn try_from(def: WorldConfigurationDef) -> Result<Self, Self::Error> {
+        let WorldConfigurationDef {
+            width,
+            height,
+            seed,
+            control_mode,
+            heights,
+            tiles,
+        } = def;
+        let heights = flatten_grid("heights", heights, width, height)?;
+        let tiles = flatten_grid("tiles", tiles, width, height)?;
+        Ok(WorldConfiguration {
+            width,
+            height,
+            seed,
+            control_mode,
+            heights,
+            tiles,
+        })
+    }
+}
+
+/// check that a grid has the dimensions of the world, and flatten it into row-major order
+///
+/// an empty grid means the world didn't provide one
+fn flatten_grid<T>(
+    name: &str,
+    grid: Vec<Vec<T>>,
+    width: usize,
+    height: usize,
+) -> Result<Option<Vec<T>>, String> {
+    if grid.is_empty() {
+        return Ok(None);
+    }
+    if grid.len() != height {
+        return Err(format!(
+            "`{name}` has {} rows, but the world height is {height}",
+            grid.len()
+        ));
+    }
+    if let Some((y, row)) = grid.iter().enumerate().find(|(_, row)| row.len() != width) {
+        return Err(format!(
+            "`{name}` row {y} has {} entries, but the world width is {width}",
+            row.len()
+        ));
+    }
+    Ok(Some(grid.into_iter().flatten().collect()))
+}
+
+impl WorldConfiguration {
+    /// the authored height of a tile, if this world has a height grid
+    pub fn tile_height(&self, x: usize, y: usize) -> Option<f32> {
+        let heights = self.heights.as_ref()?;
+        heights.get((y * self.width) + x).copied()
+    }
+    /// the authored kind of a tile, or the default kind if this world has no tile grid
+    pub fn tile_kind(&self, x: usize, y: usize) -> TileKind {
+        self.tiles
+            .as_ref()
+            .and_then(|tiles| tiles.get((y * self.width) + x))
+            .copied()
+            .unwrap_or_default()
+    }
+    /// whether a pawn may stand on this tile of the world, going by its authored kind
+    pub fn passable(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.tile_kind(x, y) != TileKind::Wall
+    }
+    /// the height of a tile, authored if this world has a height grid,
+    /// otherwise a little random jitter derived from the seed
+    pub fn generate_height(&self, rng: &GameRng, x: usize, y: usize) -> f32 {
+        self.tile_height(x, y)
+            .unwrap_or_else(|| rng.for_tile(x, y).gen_range(-0.1..0.1))
+    }
+    /// the number of chunk columns and rows needed to cover the world
+    pub fn chunk_count(&self) -> (usize, usize) {
+        (
+            self.width.div_ceil(CHUNK_SIZE),
+            self.height.div_ceil(CHUNK_SIZE),
+        )
+    }
+}
+
+/// What a board tile is made of
+#[derive(serde::Deserialize, Default, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum TileKind {
+    #[default]
+    Ground,
+    /// pawns can't enter this tile
+    Wall,
 }
 
 /// Board tile
 #[derive(Component)]
 pub struct Cell {
     pub height: f32,
+    pub kind: TileKind,
 }
 
 /// Parent of game board entities
+///
+/// holds the tiles of one chunk, addressed with board coordinates
 #[derive(Component)]
 pub struct ChunkData {
+    origin: BoardPosition,
     width: usize,
     height: usize,
     inner: Vec<Tile>,
 }
 
 impl ChunkData {
-    pub fn new(width: usize, height: usize, tiles: Vec<Tile>) -> ChunkData {
+    pub fn new(origin: BoardPosition, width: usize, height: usize, tiles: Vec<Tile>) -> ChunkData {
         ChunkData {
+            origin,
             width,
             height,
             inner: tiles,
         }
     }
+    /// whether the given board coordinate falls within this chunk
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.origin.x..self.origin.x + self.width).contains(&x)
+            && (self.origin.y..self.origin.y + self.height).contains(&y)
+    }
     pub fn get(&self, x: usize, y: usize) -> Option<&Tile> {
-        let index = (y * self.width) + x;
+        if !self.contains(x, y) {
+            return None;
+        }
+        let index = ((y - self.origin.y) * self.width) + (x - self.origin.x);
         self.inner.get(index)
     }
     pub fn size(&self) -> (usize, usize) {
@@ -46,11 +195,285 @@ impl ChunkData {
     pub fn get_height(&self, x: usize, y: usize) -> f32 {
         self.get(x, y).map(|t| t.height).unwrap_or(0.0)
     }
+    /// the height of every tile, in row order
+    pub fn heights(&self) -> Vec<f32> {
+        self.inner.iter().map(|t| t.height).collect()
+    }
 }
 
 pub struct Tile {
     pub entity: Entity,
     pub height: f32,
+    pub kind: TileKind,
+}
+
+/// Width and height of a chunk, in tiles
+pub const CHUNK_SIZE: usize = 16;
+
+/// Chunks this many chunks away from the player's chunk are kept loaded
+pub const CHUNK_LOAD_RADIUS: usize = 2;
+
+/// The position of a chunk, in units of CHUNK_SIZE tiles
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Component,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Clone,
+    Copy,
+    Debug,
+)]
+pub struct ChunkCoord {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl ChunkCoord {
+    /// the chunk which contains the given board position
+    pub fn containing(position: BoardPosition) -> ChunkCoord {
+        ChunkCoord {
+            x: position.x / CHUNK_SIZE,
+            y: position.y / CHUNK_SIZE,
+        }
+    }
+    /// the board position of this chunk's first tile
+    pub fn origin(&self) -> BoardPosition {
+        BoardPosition {
+            x: self.x * CHUNK_SIZE,
+            y: self.y * CHUNK_SIZE,
+        }
+    }
+    /// all chunks of the world within CHUNK_LOAD_RADIUS of this one, including itself
+    pub fn neighborhood(&self, config: &WorldConfiguration) -> impl Iterator<Item = ChunkCoord> {
+        let (columns, rows) = config.chunk_count();
+        let xs =
+            self.x.saturating_sub(CHUNK_LOAD_RADIUS)..(self.x + CHUNK_LOAD_RADIUS + 1).min(columns);
+        let ys =
+            self.y.saturating_sub(CHUNK_LOAD_RADIUS)..(self.y + CHUNK_LOAD_RADIUS + 1).min(rows);
+        ys.flat_map(move |y| xs.clone().map(move |x| ChunkCoord { x, y }))
+    }
+}
+
+/// Chunks which currently have entities in the world
+#[derive(Resource, Default)]
+pub struct ChunkMap {
+    loaded: BTreeMap<ChunkCoord, Entity>,
+    /// tile heights restored from a saved game, used instead of generating them
+    saved_heights: BTreeMap<ChunkCoord, Vec<f32>>,
+}
+
+impl ChunkMap {
+    /// the range of board coordinates covered by loaded chunks, clamped to the world's size
+    pub fn tile_bounds(&self, config: &WorldConfiguration) -> (Range<usize>, Range<usize>) {
+        let mut coords = self.loaded.keys();
+        let Some(first) = coords.next() else {
+            return (0..0, 0..0);
+        };
+        let (min, max) = coords.fold((*first, *first), |(min, max), c| {
+            (
+                ChunkCoord {
+                    x: min.x.min(c.x),
+                    y: min.y.min(c.y),
+                },
+                ChunkCoord {
+                    x: max.x.max(c.x),
+                    y: max.y.max(c.y),
+                },
+            )
+        });
+        let (start, end) = (min.origin(), max.origin());
+        (
+            start.x..(end.x + CHUNK_SIZE).min(config.width),
+            start.y..(end.y + CHUNK_SIZE).min(config.height),
+        )
+    }
+    /// spawn any chunks near the given position that aren't loaded yet,
+    /// and despawn loaded chunks that are too far away
+    pub fn load_around(
+        &mut self,
+        commands: &mut Commands,
+        config: &WorldConfiguration,
+        rng: &GameRng,
+        position: BoardPosition,
+    ) {
+        let wanted: Vec<ChunkCoord> = ChunkCoord::containing(position)
+            .neighborhood(config)
+            .collect();
+        self.loaded.retain(|coord, entity| {
+            let keep = wanted.contains(coord);
+            if !keep {
+                commands.entity(*entity).despawn_recursive();
+            }
+            keep
+        });
+        for coord in wanted {
+            self.loaded.entry(coord).or_insert_with(|| {
+                let heights = self.saved_heights.get(&coord).map(Vec::as_slice);
+                spawn_chunk(commands, config, rng, coord, heights)
+            });
+        }
+    }
+    /// forget every chunk, for when the world's entities have been despawned
+    pub fn clear(&mut self) {
+        self.loaded.clear();
+        self.saved_heights.clear();
+    }
+    /// the chunks that currently have entities in the world
+    pub fn loaded(&self) -> impl Iterator<Item = (ChunkCoord, Entity)> + '_ {
+        self.loaded.iter().map(|(coord, entity)| (*coord, *entity))
+    }
+    /// heights restored from a saved game for chunks that aren't loaded right now
+    pub fn saved_heights(&self) -> impl Iterator<Item = (ChunkCoord, &[f32])> {
+        self.saved_heights
+            .iter()
+            .filter(|(coord, _)| !self.loaded.contains_key(coord))
+            .map(|(coord, heights)| (*coord, heights.as_slice()))
+    }
+    /// use these tile heights for a chunk whenever it is spawned, instead of generating them
+    pub fn restore_heights(&mut self, coord: ChunkCoord, heights: Vec<f32>) {
+        self.saved_heights.insert(coord, heights);
+    }
+}
+
+/// Lookups of board tiles, routed to whichever loaded chunk holds them
+#[derive(SystemParam)]
+pub struct GameBoard<'w, 's> {
+    map: Res<'w, ChunkMap>,
+    chunks: Query<'w, 's, &'static ChunkData>,
+}
+
+impl<'w, 's> GameBoard<'w, 's> {
+    /// the loaded chunk containing this board coordinate
+    pub fn chunk(&self, x: usize, y: usize) -> Option<&ChunkData> {
+        let coord = ChunkCoord::containing(BoardPosition { x, y });
+        let entity = self.map.loaded.get(&coord)?;
+        self.chunks.get(*entity).ok()
+    }
+    pub fn get(&self, x: usize, y: usize) -> Option<&Tile> {
+        self.chunk(x, y)?.get(x, y)
+    }
+    /// the height of the tile, or 0.0 if its chunk isn't loaded
+    pub fn get_height(&self, x: usize, y: usize) -> f32 {
+        self.get(x, y).map(|t| t.height).unwrap_or(0.0)
+    }
+    /// see [`ChunkMap::tile_bounds`]
+    pub fn tile_bounds(&self, config: &WorldConfiguration) -> (Range<usize>, Range<usize>) {
+        self.map.tile_bounds(config)
+    }
+    /// whether a pawn may stand on this tile
+    ///
+    /// tiles off the board or in unloaded chunks can't be entered, and neither can walls
+    pub fn passable(&self, x: usize, y: usize) -> bool {
+        self.get(x, y)
+            .map(|tile| tile.kind != TileKind::Wall)
+            .unwrap_or(false)
+    }
+    /// the tile under this point in the world, if it's on the board and loaded
+    pub fn tile_at(&self, translation: Vec3) -> Option<&Tile> {
+        let BoardPosition { x, y } = tile_under(translation)?;
+        self.get(x, y)
+    }
+    /// the height of the ground at this point in the world,
+    /// interpolated between the heights of the four nearest tile centers
+    pub fn ground_height(&self, translation: Vec3) -> f32 {
+        let (x, z) = (translation.x.max(0.0), translation.z.max(0.0));
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (fx, fz) = (x - x0 as f32, z - z0 as f32);
+        // missing neighbors at the edge of the board take the height of the nearest tile
+        let nearest = self.get_height(x0, z0);
+        let height = |x, y| self.get(x, y).map(|t| t.height).unwrap_or(nearest);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let near = lerp(height(x0, z0), height(x0 + 1, z0), fx);
+        let far = lerp(height(x0, z0 + 1), height(x0 + 1, z0 + 1), fx);
+        lerp(near, far, fz)
+    }
+}
+
+/// the board position of the tile under a point in the world, if it isn't off the low edges
+///
+/// tile centers are at whole coordinates, so each tile spans half a unit either side
+pub fn tile_under(translation: Vec3) -> Option<BoardPosition> {
+    let x = (translation.x + 0.5).floor();
+    let y = (translation.z + 0.5).floor();
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    Some(BoardPosition {
+        x: x as usize,
+        y: y as usize,
+    })
+}
+
+/// spawn the tiles of one chunk, as children of a new chunk entity
+///
+/// tiles take their height from `saved_heights` when given, and are generated otherwise.
+/// they have no models until the presentation gives them one
+pub fn spawn_chunk(
+    commands: &mut Commands,
+    config: &WorldConfiguration,
+    rng: &GameRng,
+    coord: ChunkCoord,
+    saved_heights: Option<&[f32]>,
+) -> Entity {
+    let origin = coord.origin();
+    let width = CHUNK_SIZE.min(config.width - origin.x);
+    let height = CHUNK_SIZE.min(config.height - origin.y);
+    let chunk = commands
+        .spawn((TransformBundle::default(), coord, Scope::Game))
+        .id();
+    let tiles = (origin.y..origin.y + height)
+        .flat_map(|y| (origin.x..origin.x + width).map(move |x| (x, y)))
+        .enumerate()
+        .map(|(i, (x, y))| {
+            let height = saved_heights
+                .and_then(|heights| heights.get(i).copied())
+                .unwrap_or_else(|| config.generate_height(rng, x, y));
+            let kind = config.tile_kind(x, y);
+            let entity = commands
+                .spawn((
+                    TransformBundle::from_transform(Transform::from_xyz(
+                        x as f32,
+                        height - 0.2,
+                        y as f32,
+                    )),
+                    Cell { height, kind },
+                    BoardPosition { x, y },
+                ))
+                .id();
+            commands.entity(chunk).add_child(entity);
+            Tile {
+                entity,
+                height,
+                kind,
+            }
+        })
+        .collect();
+    commands
+        .entity(chunk)
+        .insert(ChunkData::new(origin, width, height, tiles));
+    chunk
+}
+
+/// keep the chunks around the player loaded
+pub fn stream_chunks(
+    mut commands: Commands,
+    mut map: ResMut<ChunkMap>,
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    boards: Res<Assets<WorldConfiguration>>,
+    players: Query<&BoardPosition, With<Player>>,
+) {
+    let Some(board_config) = boards.get(game.world_handle.clone()) else {
+        warn!("expected board config asset to be loaded by now");
+        return;
+    };
+    let Ok(player_position) = players.get_single() else {
+        return;
+    };
+    map.load_around(&mut commands, board_config, &rng, *player_position);
 }
 
 pub const RESET_FOCUS: [f32; 3] = [2.0 as f32 / 2.0, 0.0, 2.0 as f32 / 2.0 - 0.5];
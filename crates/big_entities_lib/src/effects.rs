@@ -0,0 +1,197 @@
+use crate::{
+    chunks::{tile_under, BoardPosition},
+    pawn::Player,
+    pickup::{Despawn, Pickup},
+    GameplaySet,
+};
+use bevy::prelude::*;
+use std::{mem::discriminant, time::Duration};
+
+/// how fast a magnet pulls pickups toward the player, in units per second
+const MAGNET_PULL_SPEED: f32 = 3.0;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (tick_status_effects, magnet_pickups)
+                .chain()
+                .in_set(GameplaySet::Effects),
+        );
+    }
+}
+
+/// What a status effect does while it lasts
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug)]
+pub enum EffectKind {
+    /// multiplies the pawn's movement speed
+    SpeedBoost(f32),
+    /// pulls pickups within this many tiles toward the player
+    Magnet(f32),
+    /// stops pickups from expiring
+    TimeFreeze,
+    /// multiplies points gained from pickups
+    ScoreMultiplier(i32),
+}
+
+impl EffectKind {
+    /// short name to show on the HUD
+    pub fn label(&self) -> &'static str {
+        match self {
+            EffectKind::SpeedBoost(_) => "Speed",
+            EffectKind::Magnet(_) => "Magnet",
+            EffectKind::TimeFreeze => "Freeze",
+            EffectKind::ScoreMultiplier(_) => "Multiplier",
+        }
+    }
+}
+
+/// What happens when an effect is gained while the same kind of effect is already active
+#[derive(serde::Deserialize, Default, Clone, Copy, Debug)]
+pub enum Stacking {
+    /// restart the existing effect's timer
+    #[default]
+    Refresh,
+    /// add the new duration to the existing effect's remaining time
+    Extend,
+    /// run both effects side by side, so their strengths combine
+    Stack,
+}
+
+/// An effect that a pickup gives, as described in the pickups file
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+pub struct EffectSpec {
+    pub kind: EffectKind,
+    /// seconds the effect lasts
+    pub duration: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+}
+
+/// An effect that is currently applied
+#[derive(Debug)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    pub timer: Timer,
+}
+
+impl ActiveEffect {
+    /// seconds until this effect wears off
+    pub fn remaining(&self) -> f32 {
+        self.timer.remaining_secs()
+    }
+}
+
+/// Temporary effects on a player, gained from power-up pickups
+#[derive(Component, Default, Debug)]
+pub struct StatusEffects(Vec<ActiveEffect>);
+
+impl StatusEffects {
+    /// gain an effect, following its stacking rule if the same kind is already active
+    pub fn apply(&mut self, effect: &EffectSpec) {
+        let duration = Duration::from_secs_f32(effect.duration.max(0.0));
+        let existing = self
+            .0
+            .iter_mut()
+            .find(|active| discriminant(&active.kind) == discriminant(&effect.kind));
+        match (existing, effect.stacking) {
+            (Some(active), Stacking::Refresh) => {
+                active.kind = effect.kind;
+                active.timer = Timer::new(duration, TimerMode::Once);
+            }
+            (Some(active), Stacking::Extend) => {
+                let total = active.timer.duration() + duration;
+                active.timer.set_duration(total);
+            }
+            (Some(_), Stacking::Stack) | (None, _) => self.0.push(ActiveEffect {
+                kind: effect.kind,
+                timer: Timer::new(duration, TimerMode::Once),
+            }),
+        }
+    }
+    /// advance every effect's timer, and drop those that have worn off
+    pub fn tick(&mut self, delta: Duration) {
+        for active in self.0.iter_mut() {
+            active.timer.tick(delta);
+        }
+        self.0.retain(|active| !active.timer.finished());
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &ActiveEffect> {
+        self.0.iter()
+    }
+    /// combined multiplier on movement speed
+    pub fn speed_factor(&self) -> f32 {
+        self.iter()
+            .filter_map(|active| match active.kind {
+                EffectKind::SpeedBoost(factor) => Some(factor),
+                _ => None,
+            })
+            .product()
+    }
+    /// combined multiplier on points gained
+    pub fn score_multiplier(&self) -> i32 {
+        self.iter()
+            .filter_map(|active| match active.kind {
+                EffectKind::ScoreMultiplier(factor) => Some(factor),
+                _ => None,
+            })
+            .product()
+    }
+    /// the largest active magnet radius
+    pub fn magnet_radius(&self) -> Option<f32> {
+        self.iter()
+            .filter_map(|active| match active.kind {
+                EffectKind::Magnet(radius) => Some(radius),
+                _ => None,
+            })
+            .reduce(f32::max)
+    }
+    pub fn time_frozen(&self) -> bool {
+        self.iter()
+            .any(|active| matches!(active.kind, EffectKind::TimeFreeze))
+    }
+}
+
+impl FromIterator<ActiveEffect> for StatusEffects {
+    fn from_iter<I: IntoIterator<Item = ActiveEffect>>(effects: I) -> Self {
+        StatusEffects(effects.into_iter().collect())
+    }
+}
+
+/// count down status effects, removing them when they wear off
+pub fn tick_status_effects(time: Res<Time>, mut effects: Query<&mut StatusEffects>) {
+    for mut effects in effects.iter_mut() {
+        effects.tick(time.delta());
+    }
+}
+
+/// pull pickups toward players with a magnet effect
+pub fn magnet_pickups(
+    time: Res<Time>,
+    players: Query<(&Transform, &StatusEffects), With<Player>>,
+    mut pickups: Query<
+        (&mut Transform, &mut BoardPosition),
+        (With<Pickup>, Without<Player>, Without<Despawn>),
+    >,
+) {
+    for (player, effects) in players.iter() {
+        let Some(radius) = effects.magnet_radius() else {
+            continue;
+        };
+        for (mut transform, mut position) in pickups.iter_mut() {
+            let mut offset = player.translation - transform.translation;
+            offset.y = 0.0;
+            let distance = offset.length();
+            if distance > radius || distance < f32::EPSILON {
+                continue;
+            }
+            let step = (MAGNET_PULL_SPEED * time.delta_seconds()).min(distance);
+            transform.translation += offset / distance * step;
+            if let Some(tile) = tile_under(transform.translation) {
+                *position = tile;
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    chunks::{BoardPosition, WorldConfiguration},
+    pawn::Player,
+    pickup::{spawn_a_pickup, PickupKinds},
+    replay::{start_playback, LastReplay, Replay, ReplayPlayback},
+    rng::SeedOverride,
+    save::DataDir,
+    states::GameState,
+    Game, SimulationPlugin, TICKS_PER_SECOND,
+};
+use bevy::{
+    ecs::system::CommandQueue,
+    input::{keyboard::KeyboardInput, ButtonState, InputPlugin},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// counts the headless games made by this process, to give each its own folder
+static GAMES_MADE: AtomicUsize = AtomicUsize::new(0);
+
+/// how much game time passes each update, unless another frame time is chosen,
+/// which is one gameplay tick
+pub const STEP: f32 = (1.0 / TICKS_PER_SECOND) as f32;
+
+/// A game without a window, renderer or audio, which is stepped and given input by hand,
+/// such as by tests
+pub struct HeadlessGame {
+    pub app: App,
+    /// game time passed each update
+    frame: Duration,
+}
+
+impl HeadlessGame {
+    /// a game in the given world, waiting to be started, which runs one tick each update
+    pub fn new(world: WorldConfiguration) -> HeadlessGame {
+        HeadlessGame::with_frame_time(world, 1.0 / TICKS_PER_SECOND)
+    }
+    /// a game in the given world, waiting to be started, which passes this many seconds
+    /// of game time each update, as if it ran at that frame rate
+    ///
+    /// the game's files are kept in a temporary folder of its own, which is removed with it,
+    /// so games running side by side don't see each other's high scores, settings or saves
+    pub fn with_frame_time(world: WorldConfiguration, seconds: f64) -> HeadlessGame {
+        let frame = Duration::from_secs_f64(seconds);
+        let dir = std::env::temp_dir().join(format!(
+            "big_entities_headless_{}_{}",
+            std::process::id(),
+            GAMES_MADE.fetch_add(1, Ordering::Relaxed)
+        ));
+        // left behind by an earlier run that didn't finish
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut app = App::new();
+        // the settings and high scores are read from it as the simulation is added
+        app.insert_resource(DataDir(Some(dir)));
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            SimulationPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+        // the seed comes from the world, not from the test's command line
+        .insert_resource(SeedOverride(None));
+        let world_handle = app
+            .world
+            .resource_mut::<Assets<WorldConfiguration>>()
+            .add(world);
+        // models are never loaded, so their handles stay empty
+        app.insert_resource(Game::new(
+            Handle::default(),
+            Handle::default(),
+            Handle::default(),
+            world_handle,
+        ))
+        .insert_resource(PickupKinds::fallback(Handle::default()));
+        app.update();
+        HeadlessGame { app, frame }
+    }
+    /// start a new game
+    pub fn start(&mut self) {
+        self.set_state(GameState::Playing);
+    }
+    /// switch to another state, and update once so the switch happens
+    pub fn set_state(&mut self, state: GameState) {
+        self.app
+            .world
+            .resource_mut::<NextState<GameState>>()
+            .set(state);
+        self.app.update();
+    }
+    pub fn state(&self) -> GameState {
+        *self.app.world.resource::<State<GameState>>().get()
+    }
+    /// run one update, a frame of game time
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+    /// how many updates it takes for this much game time to pass
+    fn frames(&self, seconds: f32) -> usize {
+        (seconds / self.frame.as_secs_f32()).ceil() as usize
+    }
+    /// run updates until this much game time has passed
+    pub fn advance(&mut self, seconds: f32) {
+        for _ in 0..self.frames(seconds) {
+            self.app.update();
+        }
+    }
+    /// run updates until this much game time has passed since the game was made,
+    /// so that input can be given at the same moments whatever the frame time
+    pub fn advance_to(&mut self, seconds: f64) {
+        let target = Duration::from_secs_f64(seconds);
+        while self.app.world.resource::<Time<Virtual>>().elapsed() < target {
+            self.app.update();
+        }
+    }
+    /// run updates until the condition holds, for at most this much game time
+    ///
+    /// returns whether the condition held
+    pub fn advance_until(
+        &mut self,
+        seconds: f32,
+        mut condition: impl FnMut(&HeadlessGame) -> bool,
+    ) -> bool {
+        for _ in 0..self.frames(seconds) {
+            if condition(self) {
+                return true;
+            }
+            self.app.update();
+        }
+        condition(self)
+    }
+    /// hold a key down, from the next update on
+    pub fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+    }
+    pub fn release(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Released);
+    }
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+    pub fn game(&self) -> &Game {
+        self.app.world.resource::<Game>()
+    }
+    pub fn game_mut(&mut self) -> Mut<'_, Game> {
+        self.app.world.resource_mut::<Game>()
+    }
+    /// where the player's pawn is, if a game is being played
+    pub fn player_position(&mut self) -> Option<BoardPosition> {
+        self.app
+            .world
+            .query_filtered::<&BoardPosition, With<Player>>()
+            .get_single(&self.app.world)
+            .ok()
+            .copied()
+    }
+    /// the folder this game keeps its files in
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.app.world.resource::<DataDir>().0.as_deref()
+    }
+    /// the replay of the last game that ended
+    pub fn last_replay(&self) -> Option<&Replay> {
+        self.app.world.resource::<LastReplay>().0.as_ref()
+    }
+    /// start playing back a replay, and update once so its game starts
+    pub fn watch(&mut self, replay: Replay) {
+        start_playback(&mut self.app.world, replay);
+        self.app.update();
+    }
+    /// whether a replay is still being played back
+    pub fn watching(&self) -> bool {
+        self.app.world.contains_resource::<ReplayPlayback>()
+    }
+    /// whether every tick of the replay being watched has been played
+    pub fn replay_played_out(&self) -> bool {
+        self.app
+            .world
+            .get_resource::<ReplayPlayback>()
+            .is_some_and(ReplayPlayback::played_out)
+    }
+    /// put a pickup of the given kind on a tile
+    pub fn spawn_pickup(&mut self, kind: usize, position: BoardPosition) -> Option<Entity> {
+        let world = &mut self.app.world;
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        let pickup = spawn_a_pickup(
+            &mut commands,
+            world.resource::<PickupKinds>(),
+            kind,
+            position.x,
+            position.y,
+            0.0,
+        );
+        queue.apply(world);
+        pickup
+    }
+}
+
+impl Drop for HeadlessGame {
+    fn drop(&mut self) {
+        if let Some(dir) = self.data_dir() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    actions::{Action, Actions, BACKSPACE},
+    replay::ReplayPlayback,
+    rng::GameRng,
+    save::{write_data_file, DataDir},
+    Game,
+};
+use bevy::{prelude::*, window::ReceivedCharacter};
+
+/// how many scores are kept
+pub const MAX_HIGH_SCORES: usize = 10;
+/// longest name that can be entered
+pub const MAX_NAME_LENGTH: usize = 10;
+/// letters that can be chosen without a keyboard, in the order they are cycled through
+const NAME_LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const HIGH_SCORE_FILE: &str = "highscores.ron";
+
+/// Loads the high score table when the game starts
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let scores = HighScores::load(app.world.resource::<DataDir>());
+        app.insert_resource(scores)
+            .init_resource::<LatestHighScore>()
+            // names are typed into the window, which headless games don't have
+            .add_event::<ReceivedCharacter>();
+    }
+}
+
+/// One finished game
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i32,
+    pub cakes: u32,
+    pub seed: u64,
+    /// seconds spent playing
+    pub duration: f32,
+    /// when the game ended, in seconds since the unix epoch
+    pub date: u64,
+}
+
+impl HighScore {
+    /// ranks by cakes eaten, since every game ends on the same score, then by score
+    fn beats(&self, other: &HighScore) -> bool {
+        (self.cakes, self.score) > (other.cakes, other.score)
+    }
+}
+
+/// The best games played on this machine, saved as RON in the platform data directory
+#[derive(serde::Deserialize, serde::Serialize, Resource, Default, Debug)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+    /// the name entered last time, offered again for the next high score
+    #[serde(default)]
+    pub last_name: String,
+}
+
+impl HighScores {
+    /// read the high score file, starting an empty table if there isn't one
+    pub fn load(dir: &DataDir) -> HighScores {
+        let Some(path) = dir.file(HIGH_SCORE_FILE) else {
+            warn!("no data directory on this platform, high scores will not be saved");
+            return HighScores::default();
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return HighScores::default();
+        };
+        match ron::from_str(&text) {
+            Ok(scores) => scores,
+            Err(e) => {
+                warn!("could not read high scores from {}: {e}", path.display());
+                HighScores::default()
+            }
+        }
+    }
+    /// write the high score file
+    pub fn save(&self, dir: &DataDir) {
+        let Some(path) = dir.file(HIGH_SCORE_FILE) else {
+            return;
+        };
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("could not serialize high scores: {e}");
+                return;
+            }
+        };
+        if let Err(e) = write_data_file(&path, &text) {
+            warn!("could not save high scores: {e}");
+        }
+    }
+    /// add a finished game to the table, returning its rank if it made the top scores
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+}
+
+/// The entry added by the game that just ended, shown highlighted on the game over screen
+#[derive(Resource, Default, Debug)]
+pub struct LatestHighScore {
+    pub rank: Option<usize>,
+    /// the player is still typing their name for this entry
+    pub entering_name: bool,
+}
+
+/// A row of the high score table, showing the entry at this rank
+#[derive(Component)]
+pub struct HighScoreRow(pub usize);
+
+/// the current time, in seconds since the unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// the current time, from the browser's clock since `SystemTime` isn't available on wasm
+#[cfg(target_arch = "wasm32")]
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// format seconds since the unix epoch as a year-month-day date
+pub fn format_date(seconds: u64) -> String {
+    // days to civil date, from Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// the text of one row of the table
+pub fn high_score_text(rank: usize, entry: &HighScore, editing: bool) -> String {
+    let cursor = if editing { "_" } else { "" };
+    let minutes = (entry.duration / 60.0).floor();
+    let seconds = entry.duration - minutes * 60.0;
+    format!(
+        "{:>2}. {:<w$} {:>4} cakes {:>6} pts  {}:{:02.0}  {}",
+        rank + 1,
+        format!("{}{cursor}", entry.name),
+        entry.cakes,
+        entry.score,
+        minutes,
+        seconds.floor(),
+        format_date(entry.date),
+        w = MAX_NAME_LENGTH + 1,
+    )
+}
+
+/// add the game that just ended to the high score table
+pub fn record_high_score(
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    dir: Res<DataDir>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut scores: ResMut<HighScores>,
+    mut latest: ResMut<LatestHighScore>,
+) {
+    // the game being replayed was already scored when it was played
+    if playback.is_some() {
+        *latest = LatestHighScore::default();
+        return;
+    }
+    let entry = HighScore {
+        name: scores.last_name.clone(),
+        score: game.score,
+        cakes: game.cake_eaten,
+        seed: rng.seed(),
+        duration: game.play_time,
+        date: now(),
+    };
+    latest.rank = scores.insert(entry);
+    latest.entering_name = latest.rank.is_some();
+    if latest.rank.is_some() {
+        scores.save(&dir);
+    }
+}
+
+/// type a name for a new high score, with the keyboard or by cycling letters on a gamepad
+pub fn enter_high_score_name(
+    actions: Actions,
+    mut typed: EventReader<ReceivedCharacter>,
+    dir: Res<DataDir>,
+    mut scores: ResMut<HighScores>,
+    mut latest: ResMut<LatestHighScore>,
+) {
+    let typed = typed.read().map(|event| event.char).collect::<Vec<_>>();
+    let Some(rank) = latest.rank.filter(|_| latest.entering_name) else {
+        return;
+    };
+    let Some(entry) = scores.entries.get_mut(rank) else {
+        latest.entering_name = false;
+        return;
+    };
+    let name = &mut entry.name;
+    for c in typed {
+        if c == BACKSPACE {
+            name.pop();
+        } else if c.is_ascii_alphanumeric() && name.len() < MAX_NAME_LENGTH {
+            name.push(c.to_ascii_uppercase());
+        }
+    }
+    if actions.just_pressed(Action::DeleteLetter) {
+        name.pop();
+    }
+    if actions.just_pressed(Action::AddLetter) && name.len() < MAX_NAME_LENGTH {
+        name.push('A');
+    }
+    let cycle = match (
+        actions.just_pressed(Action::NextLetter),
+        actions.just_pressed(Action::PreviousLetter),
+    ) {
+        (true, false) => 1,
+        (false, true) => NAME_LETTERS.len() - 1,
+        _ => 0,
+    };
+    if cycle != 0 {
+        let index = name
+            .pop()
+            .and_then(|c| NAME_LETTERS.find(c))
+            .map_or(0, |i| (i + cycle) % NAME_LETTERS.len());
+        name.extend(NAME_LETTERS[index..].chars().next());
+    }
+    if actions.just_pressed(Action::Confirm) {
+        latest.entering_name = false;
+        scores.last_name = scores.entries[rank].name.clone();
+        scores.save(&dir);
+    }
+}
+
+/// keep the highlighted row showing the name as it is typed
+pub fn update_high_score_rows(
+    scores: Res<HighScores>,
+    latest: Res<LatestHighScore>,
+    mut rows: Query<(&mut Text, &HighScoreRow)>,
+) {
+    if !scores.is_changed() && !latest.is_changed() {
+        return;
+    }
+    for (mut text, HighScoreRow(rank)) in rows.iter_mut() {
+        if Some(*rank) != latest.rank {
+            continue;
+        }
+        if let Some(entry) = scores.entries.get(*rank) {
+            text.sections[0].value = high_score_text(*rank, entry, latest.entering_name);
+        }
+    }
+}
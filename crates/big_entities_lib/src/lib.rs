@@ -1,26 +1,43 @@
-#![allow(clippy::type_complexity)]
-use assets::MyEmbeddedAssetsPlugin;
-use bevy::{
-    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
-    prelude::*,
-};
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+use actions::ActionPlugin;
+use audio::PlaySfx;
+use bevy::prelude::*;
 use bevy_common_assets::ron::RonAssetPlugin;
-use chunks::WorldConfiguration;
-use pawn::PawnPlugin;
-use pickup::PickupPlugin;
+use chunks::{ChunkPlugin, WorldConfiguration};
+use effects::EffectsPlugin;
+use highscores::{enter_high_score_name, record_high_score, HighScorePlugin};
+use pawn::{free_movement::move_pawn, gravity::apply_gravity, PawnPlugin, Teleported};
+use pickup::{PickupCatalog, PickupPlugin};
+use presentation::PresentationPlugin;
+use replay::ReplayPlugin;
+use rng::{GameRng, SeedOverride};
+use save::{DataDir, SavePlugin};
+use settings::SettingsPlugin;
 use states::{
-    game_over::{display_score, gameover_keyboard},
-    loading::setup_loading,
-    menu::create_menu_ui,
-    playing::{end_game, enter_menu, setup, teardown, update_scoreboard},
-    GameState,
+    game_over::gameover_keyboard,
+    menu::{pause_game, resume_game},
+    playing::{
+        count_play_time, end_game, enter_menu, no_game_in_progress, no_state_change_pending,
+        respawn_fallen, setup,
+    },
+    despawn_scope, GameState, Scope,
 };
-use ui::{button_clicked, trigger_check, fps::{setup_fps_counter, fps_text_update_system, fps_counter_showhide}};
 
+pub mod actions;
 pub mod assets;
+pub mod audio;
 pub mod chunks;
+pub mod effects;
+#[cfg(feature = "headless")]
+pub mod headless;
+pub mod highscores;
 pub mod pawn;
 pub mod pickup;
+pub mod presentation;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod settings;
 pub mod states;
 pub mod ui;
 
@@ -28,46 +45,138 @@ pub mod ui;
 #[derive(Resource)]
 pub struct Game {
     player_handle: Handle<Scene>,
-    pickup_handle: Handle<Scene>,
+    pickups_handle: Handle<PickupCatalog>,
     tile_handle: Handle<Scene>,
     world_handle: Handle<WorldConfiguration>,
-    score: i32,
-    cake_eaten: u32,
+    pub score: i32,
+    pub cake_eaten: u32,
+    /// seconds spent playing this game
+    pub play_time: f32,
+}
+
+impl Game {
+    pub fn new(
+        player_handle: Handle<Scene>,
+        pickups_handle: Handle<PickupCatalog>,
+        tile_handle: Handle<Scene>,
+        world_handle: Handle<WorldConfiguration>,
+    ) -> Game {
+        Game {
+            player_handle,
+            pickups_handle,
+            tile_handle,
+            world_handle,
+            score: 0,
+            cake_eaten: 0,
+            play_time: 0.0,
+        }
+    }
 }
 
+/// The whole game: the simulation, and the models, ui and sound that present it
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins((SimulationPlugin, PresentationPlugin));
+    }
+}
+
+/// how many gameplay ticks run each second of game time
+pub const TICKS_PER_SECOND: f64 = 60.0;
+
+/// The parts of a gameplay tick, which run one after another while playing, so that a game
+/// plays out the same way every time it is given the same seed and input, whatever the frame rate
+#[derive(SystemSet, Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum GameplaySet {
+    /// the input for this tick is recorded, or fed in from a replay
+    Input,
+    Movement,
+    /// status effects wear off, and act on the world
+    Effects,
+    Pickups,
+    /// scoring, and the checks for the game ending or being paused
+    Rules,
+}
+
+/// The gameplay, which needs no window, renderer or audio, so it can also run headless
+///
+/// needs the asset, input, state and time plugins, which MinimalPlugins and AssetPlugin provide.
+/// its entities have transforms but nothing to see, until the PresentationPlugin attaches those
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        // the settings and high scores are read from it as their plugins are added
+        if !app.world.contains_resource::<DataDir>() {
+            app.insert_resource(DataDir::from_env());
+        }
         app.add_plugins((
-            MyEmbeddedAssetsPlugin,
+            ActionPlugin,
+            ChunkPlugin,
+            EffectsPlugin,
+            HighScorePlugin,
             PawnPlugin,
             PickupPlugin,
+            ReplayPlugin,
+            SavePlugin,
+            SettingsPlugin,
             RonAssetPlugin::<WorldConfiguration>::new(&["world.ron"]),
         ))
+        .insert_resource(SeedOverride::from_env())
+        .init_resource::<GameRng>()
         .add_state::<GameState>()
-        .add_systems(OnEnter(GameState::Loading), setup_loading)
-        .add_systems(Update, (trigger_check, button_clicked))
-        .add_systems(OnEnter(GameState::Playing), setup)
+        .add_event::<PlaySfx>()
+        .add_event::<Teleported>()
         .add_systems(
-            Update,
-            (update_scoreboard, end_game, enter_menu).run_if(in_state(GameState::Playing)),
+            OnEnter(GameState::Playing),
+            // coming back from the menu resumes the paused game instead
+            setup.run_if(no_game_in_progress),
+        )
+        .insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND))
+        .configure_sets(
+            FixedUpdate,
+            (
+                GameplaySet::Input,
+                GameplaySet::Movement,
+                GameplaySet::Effects,
+                GameplaySet::Pickups,
+                GameplaySet::Rules,
+            )
+                .chain()
+                // once the game is ending or pausing, the rest of the frame's ticks are skipped,
+                // so that a game has the same ticks however they are spread over frames
+                .run_if(in_state(GameState::Playing).and_then(no_state_change_pending)),
         )
-        .add_systems(OnExit(GameState::Playing), teardown)
-        .add_systems(OnEnter(GameState::GameOver), display_score)
-        .add_systems(OnEnter(GameState::Menu), create_menu_ui)
         .add_systems(
-            Update,
-            gameover_keyboard.run_if(in_state(GameState::GameOver)),
+            FixedUpdate,
+            // before the pawn's view is moved, so the view jumps back along with it
+            respawn_fallen
+                .after(apply_gravity)
+                .before(move_pawn)
+                .in_set(GameplaySet::Movement),
         )
-        .add_systems(OnExit(GameState::GameOver), teardown);
-
-        #[cfg(debug_assertions)]
-        {
-            app.add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
-            app.add_systems(Startup, setup_fps_counter);
-            app.add_systems(Update, (fps_text_update_system, fps_counter_showhide));
+        .add_systems(
+            FixedUpdate,
+            (count_play_time, end_game, enter_menu)
+                .chain()
+                .in_set(GameplaySet::Rules),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            (record_high_score, despawn_scope(Scope::Game)),
+        )
+        .add_systems(OnEnter(GameState::Menu), pause_game)
+        .add_systems(OnExit(GameState::Menu), resume_game)
+        .add_systems(
+            Update,
+            // the keyboard check goes first, so confirming a name doesn't also restart
+            (gameover_keyboard, enter_high_score_name)
+                .chain()
+                .run_if(in_state(GameState::GameOver)),
+        );
+        for state in GameState::ALL {
+            app.add_systems(OnExit(state), despawn_scope(Scope::State(state)));
         }
-        
     }
 }
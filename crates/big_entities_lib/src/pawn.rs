@@ -1,36 +1,70 @@
-use crate::states::GameState;
+use crate::{chunks::RESET_FOCUS, states::GameState, GameplaySet};
 
 use self::{
     board_movement::move_pawn_board_position,
+    control_mode::switch_control_mode,
     follow_camera::{camera_follow_zoom, update_follow_camera},
     free_movement::{move_pawn, update_board_position},
+    gravity::{apply_gravity, jump_pawn, FellOffBoard},
 };
 use bevy::ecs::component::Component;
 use bevy::prelude::*;
 
 pub mod board_movement;
+pub mod control_mode;
 pub mod follow_camera;
 pub mod free_movement;
+pub mod gravity;
 pub mod third_person;
 
 pub struct PawnPlugin;
 
 impl Plugin for PawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                move_pawn_board_position,
-                move_pawn,
-                update_follow_camera,
-                camera_follow_zoom,
-                update_board_position,
+        app.add_event::<FellOffBoard>()
+            .add_systems(Startup, spawn_view)
+            .add_systems(
+                FixedUpdate,
+                (
+                    switch_control_mode,
+                    move_pawn_board_position,
+                    jump_pawn,
+                    apply_gravity,
+                    move_pawn,
+                    update_board_position,
+                    update_follow_camera,
+                )
+                    .chain()
+                    .in_set(GameplaySet::Movement),
             )
-                .run_if(in_state(GameState::Playing)),
-        );
+            .add_systems(
+                Update,
+                camera_follow_zoom.run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
+/// Sent by gameplay when it puts an entity somewhere else at once, instead of moving it there
+#[derive(Event)]
+pub struct Teleported {
+    pub entity: Entity,
+}
+
+/// The viewpoint that pawns look through and follow cameras move, which the presentation
+/// puts a camera on
+#[derive(Component)]
+pub struct PlayerView;
+
+/// spawn the viewpoint, looking over the corner of the board until a game starts
+fn spawn_view(mut commands: Commands) {
+    commands.spawn((
+        PlayerView,
+        TransformBundle::from_transform(
+            Transform::from_xyz(-2.0, 8.0 / 3.0, 1.5).looking_at(Vec3::from(RESET_FOCUS), Vec3::Y),
+        ),
+    ));
+}
+
 /// Marker for players
 #[derive(Component)]
 pub struct Player;
@@ -1,40 +1,47 @@
 use crate::{
-    chunks::{WorldConfiguration, ChunkData, BoardPosition},
+    actions::{Action, Actions},
+    audio::{PlaySfx, Sfx},
+    chunks::{BoardPosition, GameBoard, WorldConfiguration},
+    effects::StatusEffects,
     pawn::third_person::Facing,
     Game,
 };
 use bevy::prelude::*;
 use std::f32::consts::PI;
 
-use super::{InputRateLimit, Player};
+use super::{
+    gravity::{VerticalMotion, HOP_SPEED},
+    InputRateLimit, Player,
+};
 
 /// process input to move the player pawn
 pub fn move_pawn_board_position(
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Actions,
     mut players: Query<
         (
             &mut Transform,
             &mut BoardPosition,
             &mut InputRateLimit,
             &mut Facing,
+            Option<&mut VerticalMotion>,
+            Option<&StatusEffects>,
         ),
         With<Player>,
     >,
-    gameboard: Query<&ChunkData>,
+    board: GameBoard,
     game: Res<Game>,
     boards: Res<Assets<WorldConfiguration>>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
     let Some(board_config) = boards.get(game.world_handle.clone()) else {
         warn!("expected board config asset to be loaded by now");
         return;
     };
-    let Ok(board_cache) = gameboard.get_single() else {
-        warn!("unexpected number of game boards");
-        return;
-    };
-    for (mut t, mut player, mut irl, mut facing) in players.iter_mut() {
-        if !irl.0.tick(time.delta()).finished() {
+    for (mut t, mut player, mut irl, mut facing, motion, effects) in players.iter_mut() {
+        // speed boosts let board pawns move again sooner
+        let speed = effects.map_or(1.0, StatusEffects::speed_factor).max(0.0);
+        if !irl.0.tick(time.delta().mul_f32(speed)).finished() {
             continue;
         }
 
@@ -54,32 +61,46 @@ pub fn move_pawn_board_position(
         };
 
         if let Some((x_delta, y_delta, rotation, face)) = {
-            if keyboard_input.pressed(KeyCode::Up) {
+            if actions.pressed(Action::MoveForward) {
                 Some(directions[0])
-            } else if keyboard_input.pressed(KeyCode::Down) {
+            } else if actions.pressed(Action::MoveBack) {
                 Some(directions[1])
-            } else if keyboard_input.pressed(KeyCode::Right) {
+            } else if actions.pressed(Action::TurnRight) {
                 Some(directions[2])
-            } else if keyboard_input.pressed(KeyCode::Left) {
+            } else if actions.pressed(Action::TurnLeft) {
                 Some(directions[3])
             } else {
                 None
             }
         } {
             *facing = face;
-            player.x = (player.x as i32 + x_delta)
+            let x = (player.x as i32 + x_delta)
                 .min(board_config.width as i32 - 1)
                 .max(0) as usize;
-            player.y = (player.y as i32 + y_delta)
+            let y = (player.y as i32 + y_delta)
                 .min(board_config.height as i32 - 1)
                 .max(0) as usize;
+            // turn to face walls, but don't walk into them
+            let moved = board.passable(x, y);
+            if moved {
+                player.x = x;
+                player.y = y;
+                sfx.send(PlaySfx(Sfx::Footstep));
+            }
             irl.0.reset();
+            // pawns with vertical motion hop to the new tile and let gravity land them
+            let height = match motion {
+                Some(mut motion) => {
+                    if moved {
+                        motion.velocity = HOP_SPEED;
+                        motion.grounded = false;
+                    }
+                    t.translation.y
+                }
+                None => board.get_height(player.x, player.y),
+            };
             let new_transform = Transform {
-                translation: Vec3::new(
-                    player.x as f32,
-                    board_cache.get_height(player.x, player.y),
-                    player.y as f32,
-                ),
+                translation: Vec3::new(player.x as f32, height, player.y as f32),
                 rotation: Quat::from_rotation_y(rotation),
                 ..default()
             };
@@ -0,0 +1,94 @@
+use super::{
+    follow_camera::FollowMe,
+    free_movement::FirstPersonPawn,
+    third_person::{Facing, ThirdPersonPawnBundle},
+    InputRateLimit, Player, PlayerView, Teleported,
+};
+use crate::{
+    actions::{Action, Actions},
+    chunks::BoardPosition,
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+/// The ways a player can control their pawn
+#[derive(
+    serde::Deserialize, serde::Serialize, Component, Default, Eq, PartialEq, Clone, Copy, Debug,
+)]
+pub enum ControlMode {
+    /// move freely, looking through the pawn's eyes
+    #[default]
+    FirstPerson,
+    /// step from tile to tile, seen from above
+    Board,
+    /// step from tile to tile, with the camera following behind
+    ThirdPerson,
+}
+
+impl ControlMode {
+    /// the mode that the switch action changes to from this one
+    pub fn next(&self) -> ControlMode {
+        match self {
+            ControlMode::FirstPerson => ControlMode::Board,
+            ControlMode::Board => ControlMode::ThirdPerson,
+            ControlMode::ThirdPerson => ControlMode::FirstPerson,
+        }
+    }
+}
+
+/// swap a pawn's controller components for those of the given mode
+///
+/// grid modes snap the pawn to its tile and the nearest facing, keeping its position and direction
+pub fn apply_control_mode(
+    pawn: &mut EntityCommands,
+    mode: ControlMode,
+    camera: Entity,
+    transform: &mut Transform,
+    position: BoardPosition,
+) {
+    pawn.remove::<(FirstPersonPawn, Facing, InputRateLimit, FollowMe)>()
+        .insert(mode);
+    if mode == ControlMode::FirstPerson {
+        pawn.insert(FirstPersonPawn::new(camera));
+        return;
+    }
+    let facing = Facing::from_rotation(transform.rotation);
+    transform.translation.x = position.x as f32;
+    transform.translation.z = position.y as f32;
+    transform.rotation = facing.rotation();
+    let follow = match mode {
+        ControlMode::Board => FollowMe::overhead(camera),
+        _ => FollowMe::new(camera),
+    };
+    pawn.insert((ThirdPersonPawnBundle::new(facing), follow));
+}
+
+/// cycle the player through the control modes when the switch action is pressed
+///
+/// the pawn snaps onto its tile, and the view jumps to where the new mode looks from
+pub fn switch_control_mode(
+    actions: Actions,
+    mut commands: Commands,
+    cameras: Query<Entity, With<PlayerView>>,
+    mut players: Query<(Entity, &ControlMode, &mut Transform, &BoardPosition), With<Player>>,
+    mut teleported: EventWriter<Teleported>,
+) {
+    if !actions.just_pressed(Action::SwitchControls) {
+        return;
+    }
+    let Ok(camera) = cameras.get_single() else {
+        warn!("unexpected camera count");
+        return;
+    };
+    for (entity, mode, mut transform, position) in players.iter_mut() {
+        let next = mode.next();
+        info!("switching {entity:?} to {next:?} controls");
+        apply_control_mode(
+            &mut commands.entity(entity),
+            next,
+            camera,
+            &mut transform,
+            *position,
+        );
+        teleported.send_batch([entity, camera].map(|entity| Teleported { entity }));
+    }
+}
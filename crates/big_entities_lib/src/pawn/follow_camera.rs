@@ -1,3 +1,5 @@
+use super::PlayerView;
+use crate::settings::Settings;
 use bevy::{input::mouse::MouseWheel, prelude::*};
 
 /// Animate camera to follow entity
@@ -6,6 +8,8 @@ pub struct FollowMe {
     pub camera: Entity,
     pub zoom: f32,
     pub offset: Vec3,
+    /// view from this fixed direction, instead of from behind the entity
+    pub view_from: Option<Vec3>,
     // rotation: Quat,
 }
 
@@ -16,15 +20,23 @@ impl FollowMe {
             zoom: 10.0,
             /// offset from the pawn's location to look at
             offset: Vec3::ZERO,
+            view_from: None,
             // rotation: Quat::from_rotation_x(0.0),
         }
     }
+    /// look down on the entity from a fixed angle, so the board doesn't turn with it
+    pub fn overhead(camera: Entity) -> FollowMe {
+        FollowMe {
+            view_from: Some(Vec3::NEG_X),
+            ..FollowMe::new(camera)
+        }
+    }
 }
 
 pub fn update_follow_camera(
     time: Res<Time>,
-    mut cameras: Query<&mut Transform, With<Camera>>,
-    follow: Query<(&Transform, &FollowMe), Without<Camera>>,
+    mut cameras: Query<&mut Transform, With<PlayerView>>,
+    follow: Query<(&Transform, &FollowMe), Without<PlayerView>>,
 ) {
     for (
         transform,
@@ -32,6 +44,7 @@ pub fn update_follow_camera(
             camera,
             zoom,
             offset,
+            view_from,
         },
     ) in follow.iter()
     {
@@ -41,7 +54,8 @@ pub fn update_follow_camera(
         };
         let desired_position = {
             let above = Vec3::new(0.0, *zoom, 0.0);
-            let camera_baseline = transform.translation + (transform.back() * *zoom) + above;
+            let back = view_from.unwrap_or_else(|| transform.back());
+            let camera_baseline = transform.translation + (back * *zoom) + above;
             // Transform::from_translation(camera_baseline).looking_at(transform.translation, Vec3::Z)
             camera_baseline
         };
@@ -54,7 +68,13 @@ pub fn update_follow_camera(
     }
 }
 
-pub fn camera_follow_zoom(mut wheel: EventReader<MouseWheel>, mut follow: Query<&mut FollowMe>) {
+/// zoom follow cameras with the mouse wheel, within the limits in the settings
+pub fn camera_follow_zoom(
+    settings: Res<Settings>,
+    mut wheel: EventReader<MouseWheel>,
+    mut follow: Query<&mut FollowMe>,
+) {
+    let (zoom_min, zoom_max) = settings.zoom_range();
     for MouseWheel { unit, y, .. } in wheel.read() {
         use bevy::input::mouse::MouseScrollUnit as MSU;
         let scroll = match unit {
@@ -62,14 +82,7 @@ pub fn camera_follow_zoom(mut wheel: EventReader<MouseWheel>, mut follow: Query<
             MSU::Pixel => *y,
         };
         for mut follow in follow.iter_mut() {
-            if follow.zoom >= 3.0 && follow.zoom <= 100.0 {
-                follow.zoom += scroll / 10.0;
-            }
-            if follow.zoom < 3.0 {
-                follow.zoom = 3.0;
-            } else if follow.zoom > 100.0 {
-                follow.zoom = 100.0;
-            }
+            follow.zoom = (follow.zoom + scroll / 10.0).clamp(zoom_min, zoom_max);
         }
     }
 }
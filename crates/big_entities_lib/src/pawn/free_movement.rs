@@ -1,8 +1,21 @@
-use super::Player;
-use crate::chunks::BoardPosition;
-use bevy::{input::mouse::MouseMotion, prelude::*};
+use super::{gravity::VerticalMotion, Player, PlayerView};
+use crate::{
+    actions::{Action, Actions},
+    audio::{PlaySfx, Sfx},
+    chunks::{BoardPosition, GameBoard, TileKind, WorldConfiguration},
+    effects::StatusEffects,
+    Game,
+};
+use bevy::prelude::*;
 use std::f32::consts::PI;
 
+/// how fast the turn actions rotate the pawn, in radians per second
+const KEY_TURN_SPEED: f32 = 2.0;
+/// how far the pawn turns per unit of mouse movement, before mouse sensitivity is applied
+pub const MOUSE_TURN_SPEED: f32 = 0.05;
+/// how far the pawn walks between footstep sounds
+const STRIDE: f32 = 0.8;
+
 #[derive(Component)]
 pub struct FirstPersonPawn {
     camera: Entity,
@@ -13,9 +26,11 @@ pub struct FirstPersonPawn {
     look_dist: f32,
     pub look_rotation: f32,
     /// limit the player's rotation to this speed per second
-    turn_speed: f32,
+    pub turn_speed: f32,
     /// limit the player's movement speed per second
     pub speed: f32,
+    /// distance walked since the last footstep
+    stride: f32,
 }
 
 impl FirstPersonPawn {
@@ -26,8 +41,9 @@ impl FirstPersonPawn {
             camera_follow: -0.25,
             look_dist: 10.0,
             look_rotation: 0.0,
-            turn_speed: 0.05,
+            turn_speed: MOUSE_TURN_SPEED,
             speed: 10.0,
+            stride: 0.0,
         }
     }
 }
@@ -35,29 +51,68 @@ impl FirstPersonPawn {
 /// process input to move the player pawn
 pub fn move_pawn(
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut mouse_input: EventReader<MouseMotion>,
-    mut pawns: Query<(&mut Transform, &mut FirstPersonPawn, Entity), With<Player>>,
-    mut cameras: Query<&mut Transform, (With<Camera>, Without<Player>)>,
+    actions: Actions,
+    mut pawns: Query<
+        (
+            &mut Transform,
+            &mut FirstPersonPawn,
+            Option<&VerticalMotion>,
+            Option<&StatusEffects>,
+            Entity,
+        ),
+        With<Player>,
+    >,
+    mut cameras: Query<&mut Transform, (With<PlayerView>, Without<Player>)>,
+    board: GameBoard,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    let mouse_motion = mouse_input.read().fold(Vec2::ZERO, |a, d| a + d.delta);
-    for (mut transform, mut pawn, entity) in pawns.iter_mut() {
+    let mouse_motion = actions.mouse_look();
+    for (mut transform, mut pawn, vertical, effects, entity) in pawns.iter_mut() {
         let mut delta = Vec3::ZERO;
-        if keyboard_input.pressed(KeyCode::W) {
+        if actions.pressed(Action::MoveForward) {
             delta += transform.forward();
         }
-        if keyboard_input.pressed(KeyCode::A) {
+        if actions.pressed(Action::StrafeLeft) {
             delta += transform.left();
         }
-        if keyboard_input.pressed(KeyCode::S) {
+        if actions.pressed(Action::MoveBack) {
             delta += transform.back();
         }
-        if keyboard_input.pressed(KeyCode::D) {
+        if actions.pressed(Action::StrafeRight) {
             delta += transform.right();
         }
+        let stick = actions.move_stick();
+        delta += transform.right() * stick.x + transform.forward() * stick.y;
         delta.y = 0.0;
-        let delta = delta.normalize_or_zero();
-        transform.translation += delta * pawn.speed * time.delta_seconds();
+        // sticks can move slower than full speed, but nothing moves faster
+        let delta = delta.clamp_length_max(1.0);
+        let speed = pawn.speed * effects.map_or(1.0, StatusEffects::speed_factor);
+        let step = delta * speed * time.delta_seconds();
+        let airborne = vertical.is_some_and(|v| !v.grounded);
+        let start = transform.translation;
+        // move along each axis separately, so the pawn slides along walls instead of sticking
+        for axis_step in [Vec3::new(step.x, 0.0, 0.0), Vec3::new(0.0, 0.0, step.z)] {
+            let next = transform.translation + axis_step;
+            let can_enter = match board.tile_at(next) {
+                Some(tile) => tile.kind != TileKind::Wall,
+                // pawns can't walk off the edge of the board, but they can jump off it
+                None => airborne,
+            };
+            if can_enter {
+                transform.translation = next;
+            }
+        }
+        if !airborne {
+            pawn.stride += (transform.translation - start).length();
+            if pawn.stride >= STRIDE {
+                pawn.stride -= STRIDE;
+                sfx.send(PlaySfx(Sfx::Footstep));
+            }
+        }
+        // pawns without vertical motion just follow the ground
+        if vertical.is_none() {
+            transform.translation.y = board.ground_height(transform.translation);
+        }
         let motion_filtered = {
             let Vec2 { x, y } = mouse_motion;
             Vec2 {
@@ -65,9 +120,18 @@ pub fn move_pawn(
                 y: y.min(1000.0).max(-1000.0),
             }
         };
-        let motion =
-            (motion_filtered * pawn.turn_speed) * time.delta_seconds() * Vec2::new(-1.0, -1.0);
-        transform.rotate_local_y(motion.x);
+        let motion = (motion_filtered * pawn.turn_speed * Vec2::new(-1.0, -1.0)
+            + actions.look_stick() * Vec2::new(-1.0, 1.0))
+            * time.delta_seconds();
+        let key_turn = match (
+            actions.pressed(Action::TurnLeft),
+            actions.pressed(Action::TurnRight),
+        ) {
+            (true, false) => KEY_TURN_SPEED,
+            (false, true) => -KEY_TURN_SPEED,
+            _ => 0.0,
+        };
+        transform.rotate_local_y(motion.x + key_turn * time.delta_seconds());
         let Ok(mut camera_transform) = cameras.get_mut(pawn.camera) else {
             warn!("Pawn {entity:?}'s camera was not found");
             continue;
@@ -92,10 +156,18 @@ pub fn move_pawn(
 }
 
 pub fn update_board_position(
+    game: Res<Game>,
+    boards: Res<Assets<WorldConfiguration>>,
     mut pawns: Query<(&mut Transform, &mut BoardPosition), With<FirstPersonPawn>>,
 ) {
+    let Some(board_config) = boards.get(game.world_handle.clone()) else {
+        warn!("expected board config asset to be loaded by now");
+        return;
+    };
+    let max_x = board_config.width.saturating_sub(1) as f32;
+    let max_y = board_config.height.saturating_sub(1) as f32;
     for (transform, mut board_position) in pawns.iter_mut() {
-        board_position.x = (transform.translation.x + 0.5).floor().max(0.0) as usize;
-        board_position.y = (transform.translation.z + 0.5).floor().max(0.0) as usize;
+        board_position.x = (transform.translation.x + 0.5).floor().clamp(0.0, max_x) as usize;
+        board_position.y = (transform.translation.z + 0.5).floor().clamp(0.0, max_y) as usize;
     }
 }
@@ -0,0 +1,92 @@
+use super::{free_movement::FirstPersonPawn, Player};
+use crate::{
+    actions::{Action, Actions},
+    chunks::GameBoard,
+};
+use bevy::prelude::*;
+
+/// downward acceleration, in units per second per second
+pub const GRAVITY: f32 = 20.0;
+/// upward speed given by a jump
+pub const JUMP_SPEED: f32 = 6.0;
+/// upward speed given to board pawns each time they hop to another tile
+pub const HOP_SPEED: f32 = 3.0;
+/// pawns this far below the ground have fallen off the board
+pub const FALL_DEPTH: f32 = -10.0;
+/// grounded pawns stick to ground that drops away by less than this, instead of falling
+const SNAP_DISTANCE: f32 = 0.2;
+
+/// Vertical motion of a pawn, pulled down by gravity and stopped by the tiles of the board
+#[derive(Component, serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct VerticalMotion {
+    pub velocity: f32,
+    pub grounded: bool,
+    /// where this pawn last stood on the board, to put it back after a fall
+    pub last_ground: Vec3,
+}
+
+impl VerticalMotion {
+    pub fn new(position: Vec3) -> VerticalMotion {
+        VerticalMotion {
+            velocity: 0.0,
+            grounded: true,
+            last_ground: position,
+        }
+    }
+}
+
+/// Sent when a pawn falls off the edge of the board
+#[derive(Event, Debug)]
+pub struct FellOffBoard {
+    pub entity: Entity,
+}
+
+/// process input to make first person pawns jump
+pub fn jump_pawn(
+    actions: Actions,
+    mut pawns: Query<&mut VerticalMotion, (With<FirstPersonPawn>, With<Player>)>,
+) {
+    if !actions.just_pressed(Action::Jump) {
+        return;
+    }
+    for mut motion in pawns.iter_mut() {
+        if motion.grounded {
+            motion.velocity = JUMP_SPEED;
+            motion.grounded = false;
+        }
+    }
+}
+
+/// move pawns vertically, landing them on the board or reporting when they fall off it
+pub fn apply_gravity(
+    time: Res<Time>,
+    board: GameBoard,
+    mut fell: EventWriter<FellOffBoard>,
+    mut pawns: Query<(&mut Transform, &mut VerticalMotion, Entity)>,
+) {
+    for (mut transform, mut motion, entity) in pawns.iter_mut() {
+        let ground = board
+            .tile_at(transform.translation)
+            .map(|_| board.ground_height(transform.translation));
+        motion.velocity -= GRAVITY * time.delta_seconds();
+        transform.translation.y += motion.velocity * time.delta_seconds();
+
+        let Some(ground) = ground else {
+            motion.grounded = false;
+            if transform.translation.y < FALL_DEPTH {
+                fell.send(FellOffBoard { entity });
+            }
+            continue;
+        };
+        let falling = motion.velocity <= 0.0;
+        let snap = motion.grounded && transform.translation.y - ground < SNAP_DISTANCE;
+        if falling && (transform.translation.y <= ground || snap) {
+            transform.translation.y = ground;
+            motion.velocity = 0.0;
+            motion.grounded = true;
+            motion.last_ground = transform.translation;
+        } else {
+            motion.grounded = false;
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use bevy::ecs::{bundle::Bundle, component::Component};
+use bevy::{
+    ecs::{bundle::Bundle, component::Component},
+    prelude::*,
+};
+use std::{f32::consts::PI, time::Duration};
 
 use super::{InputRateLimit, Player};
 
@@ -10,6 +14,19 @@ pub struct ThirdPersonPawnBundle {
     input_rate_limit: InputRateLimit,
 }
 
+impl ThirdPersonPawnBundle {
+    pub fn new(facing: Facing) -> ThirdPersonPawnBundle {
+        ThirdPersonPawnBundle {
+            player: Player,
+            facing,
+            input_rate_limit: InputRateLimit(Timer::new(
+                Duration::from_millis(200),
+                TimerMode::Once,
+            )),
+        }
+    }
+}
+
 #[derive(Component, Copy, Clone, Debug)]
 pub enum Facing {
     Up,
@@ -17,3 +34,28 @@ pub enum Facing {
     Right,
     Left,
 }
+
+impl Facing {
+    /// the rotation of a pawn facing this way
+    pub fn rotation(&self) -> Quat {
+        let angle = match self {
+            Facing::Up => -PI / 2.0,
+            Facing::Down => PI / 2.0,
+            Facing::Left => PI,
+            Facing::Right => 0.0,
+        };
+        Quat::from_rotation_y(angle)
+    }
+    /// the facing closest to the way a rotation points
+    pub fn from_rotation(rotation: Quat) -> Facing {
+        let forward = rotation * Vec3::NEG_Z;
+        [Facing::Up, Facing::Down, Facing::Left, Facing::Right]
+            .into_iter()
+            .max_by(|a, b| {
+                let a = forward.dot(a.rotation() * Vec3::NEG_Z);
+                let b = forward.dot(b.rotation() * Vec3::NEG_Z);
+                a.total_cmp(&b)
+            })
+            .unwrap_or(Facing::Up)
+    }
+}
@@ -1,31 +1,159 @@
 use crate::{
-    chunks::{WorldConfiguration, ChunkData, BoardPosition},
+    audio::{PlaySfx, Sfx},
+    chunks::{BoardPosition, GameBoard, WorldConfiguration},
+    effects::{EffectSpec, StatusEffects},
     pawn::Player,
-    Game,
+    rng::GameRng,
+    states::Scope,
+    Game, GameplaySet,
 };
 use bevy::prelude::*;
-use rand::Rng;
+use bevy_common_assets::ron::RonAssetPlugin;
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::ops::Range;
+
+/// how many random tiles are tried for a new pickup before giving up on it
+const PICKUP_TILE_TRIES: usize = 100;
 
 pub struct PickupPlugin;
 
 impl Plugin for PickupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                pickup_collide,
-                expire_pickups,
-                rotate_pickups,
-                respawn_pickups,
-            )
-                .chain(),
-        );
+        app.add_plugins(RonAssetPlugin::<PickupCatalog>::new(&["pickups.ron"]))
+            .init_resource::<PickupKinds>()
+            .add_systems(Update, apply_pickup_catalog)
+            .add_systems(
+                FixedUpdate,
+                (
+                    pickup_collide,
+                    expire_pickups,
+                    rotate_pickups,
+                    respawn_pickups,
+                )
+                    .chain()
+                    .in_set(GameplaySet::Pickups),
+            );
     }
 }
 
 /// Marker for components that can be pickup up
 #[derive(Component)]
-pub struct Pickup;
+pub struct Pickup {
+    /// index into PickupKinds
+    pub kind: usize,
+}
+
+/// A kind of pickup, as described in the pickups file
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PickupKind {
+    pub name: String,
+    /// asset path of the scene to show, such as `models/food/apple.glb#Scene0`
+    pub model: String,
+    /// points gained when it is collected
+    pub score: i32,
+    /// points lost when it expires
+    #[serde(default = "default_penalty")]
+    pub penalty: i32,
+    /// seconds before it expires
+    pub lifetime: f32,
+    /// chance of this kind appearing, relative to the other kinds
+    pub weight: u32,
+    /// status effect given to the player who collects it
+    #[serde(default)]
+    pub effect: Option<EffectSpec>,
+}
+
+fn default_penalty() -> i32 {
+    10
+}
+
+/// All the kinds of pickup, loaded from a `.pickups.ron` file
+#[derive(serde::Deserialize, bevy::asset::Asset, bevy::reflect::TypePath)]
+pub struct PickupCatalog {
+    pub kinds: Vec<PickupKind>,
+}
+
+/// The kinds of pickup currently in play, and their models
+#[derive(Resource, Default)]
+pub struct PickupKinds {
+    kinds: Vec<PickupKind>,
+    models: Vec<Handle<Scene>>,
+    weights: Option<WeightedIndex<u32>>,
+}
+
+impl PickupKinds {
+    /// start loading the models of the given kinds
+    pub fn new(kinds: Vec<PickupKind>, asset_server: &AssetServer) -> PickupKinds {
+        let models = kinds
+            .iter()
+            .map(|kind| asset_server.load(kind.model.clone()))
+            .collect();
+        let weights = WeightedIndex::new(kinds.iter().map(|kind| kind.weight)).ok();
+        if weights.is_none() {
+            warn!("no pickup kind has any weight, so none will spawn");
+        }
+        PickupKinds {
+            kinds,
+            models,
+            weights,
+        }
+    }
+    /// just the birthday cake, for before the pickups file has loaded
+    pub fn fallback(cake: Handle<Scene>) -> PickupKinds {
+        PickupKinds {
+            kinds: vec![PickupKind {
+                name: "Birthday Cake".to_string(),
+                model: String::new(),
+                score: 1,
+                penalty: default_penalty(),
+                lifetime: 5.0,
+                weight: 1,
+                effect: None,
+            }],
+            models: vec![cake],
+            weights: WeightedIndex::new([1]).ok(),
+        }
+    }
+    pub fn get(&self, kind: usize) -> Option<&PickupKind> {
+        self.kinds.get(kind)
+    }
+    /// the kind with this name, and its index
+    pub fn find(&self, name: &str) -> Option<(usize, &PickupKind)> {
+        self.kinds
+            .iter()
+            .enumerate()
+            .find(|(_, kind)| kind.name == name)
+    }
+    pub fn model(&self, kind: usize) -> Option<&Handle<Scene>> {
+        self.models.get(kind)
+    }
+    /// pick a kind at random, according to their weights
+    pub fn choose(&self, rng: &mut impl Rng) -> Option<usize> {
+        self.weights.as_ref().map(|weights| weights.sample(rng))
+    }
+}
+
+/// replace the pickup kinds whenever the pickups file is loaded or changed
+fn apply_pickup_catalog(
+    mut events: EventReader<AssetEvent<PickupCatalog>>,
+    game: Option<Res<Game>>,
+    asset_server: Res<AssetServer>,
+    catalogs: Res<Assets<PickupCatalog>>,
+    mut kinds: ResMut<PickupKinds>,
+) {
+    let Some(game) = game else {
+        return;
+    };
+    for event in events.read() {
+        if event.is_loaded_with_dependencies(&game.pickups_handle)
+            || event.is_modified(&game.pickups_handle)
+        {
+            if let Some(catalog) = catalogs.get(&game.pickups_handle) {
+                *kinds = PickupKinds::new(catalog.kinds.clone(), &asset_server);
+            }
+        }
+    }
+}
 
 /// Entities with this component will be reset to another BoardPosition when the timer expires
 #[derive(Component)]
@@ -35,54 +163,67 @@ pub struct AutoRespawn(pub Timer);
 #[derive(Component)]
 pub struct Despawn;
 
+/// spawn a pickup of the given kind on a tile, which has no model until the presentation gives it one
 pub fn spawn_a_pickup(
     commands: &mut Commands,
-    pickup_handle: Handle<Scene>,
+    kinds: &PickupKinds,
+    kind: usize,
     x: usize,
     y: usize,
     height: f32,
-) {
-    commands
+) -> Option<Entity> {
+    let Some(pickup_kind) = kinds.get(kind) else {
+        warn!("tried to spawn unknown pickup kind {kind}");
+        return None;
+    };
+    let pickup = commands
         .spawn((
-            Pickup,
-            AutoRespawn(Timer::from_seconds(5.0, TimerMode::Once)),
+            Scope::Game,
+            Pickup { kind },
+            AutoRespawn(Timer::from_seconds(pickup_kind.lifetime, TimerMode::Once)),
             BoardPosition {
                 x: x as usize,
                 y: y as usize,
             },
-            SceneBundle {
-                transform: Transform::from_xyz(x as f32, height, y as f32),
-                scene: pickup_handle,
-                ..default()
-            },
+            TransformBundle::from_transform(Transform::from_xyz(x as f32, height, y as f32)),
         ))
-        .with_children(|children| {
-            children.spawn(PointLightBundle {
-                point_light: PointLight {
-                    color: Color::rgb(1.0, 1.0, 0.0),
-                    intensity: 1000.0,
-                    range: 10.0,
-                    ..default()
-                },
-                transform: Transform::from_xyz(0.0, 2.0, 0.0),
-                ..default()
-            });
-        });
+        .id();
+    Some(pickup)
+}
+
+/// a random tile within the bounds that `allowed` accepts, or none if no such tile turned up
+/// in a bounded number of tries
+pub fn choose_pickup_tile(
+    rng: &mut impl Rng,
+    (xs, ys): (Range<usize>, Range<usize>),
+    allowed: impl Fn(usize, usize) -> bool,
+) -> Option<BoardPosition> {
+    if xs.is_empty() || ys.is_empty() {
+        return None;
+    }
+    (0..PICKUP_TILE_TRIES)
+        .map(|_| (rng.gen_range(xs.clone()), rng.gen_range(ys.clone())))
+        .find(|&(x, y)| allowed(x, y))
+        .map(|(x, y)| BoardPosition { x, y })
 }
 
 /// respawn pickups that are marked for removal
 fn respawn_pickups(
     mut commands: Commands,
     game: ResMut<Game>,
+    kinds: Res<PickupKinds>,
+    mut rng: ResMut<GameRng>,
     boards: Res<Assets<WorldConfiguration>>,
     players: Query<&BoardPosition, With<Player>>,
     pickups: Query<(&BoardPosition, Entity), (With<Pickup>, With<Despawn>, With<AutoRespawn>)>,
-    gameboard: Query<&ChunkData>,
+    board: GameBoard,
 ) {
     let Some(board_config) = boards.get(game.world_handle.clone()) else {
         warn!("expected board config asset to be loaded by now");
         return;
     };
+    // pickups only respawn where the world is loaded, near the player
+    let bounds = board.tile_bounds(board_config);
     let mut restricted_coordinates = players
         .iter()
         .map(|p| (p.x, p.y))
@@ -92,43 +233,54 @@ fn respawn_pickups(
     for (_, entity) in pickups.iter() {
         commands.entity(entity).despawn_recursive();
 
-        let Ok(board_cache) = gameboard.get_single() else {
-            warn!("unexpected number of game boards");
-            return;
+        let Some(pickup_coord) = choose_pickup_tile(&mut *rng, bounds.clone(), |x, y| {
+            board.passable(x, y) && !restricted_coordinates.contains(&(x, y))
+        }) else {
+            warn!("found no free tile to respawn a pickup on");
+            continue;
         };
+        restricted_coordinates.push((pickup_coord.x, pickup_coord.y));
 
-        let pickup_coord = loop {
-            let x = rand::thread_rng().gen_range(0..board_config.width);
-            let y = rand::thread_rng().gen_range(0..board_config.height);
-            if !restricted_coordinates.contains(&(x, y)) {
-                restricted_coordinates.push((x, y));
-                break BoardPosition { x, y };
-            }
+        let Some(kind) = kinds.choose(&mut *rng) else {
+            continue;
         };
-
         spawn_a_pickup(
             &mut commands,
-            game.pickup_handle.clone(),
+            &kinds,
+            kind,
             pickup_coord.x,
             pickup_coord.y,
-            board_cache.get_height(pickup_coord.x, pickup_coord.y) + 0.2,
+            board.get_height(pickup_coord.x, pickup_coord.y) + 0.2,
         );
     }
 }
 
 /// Trigger pickups to automatically despawn if their timer expires
+///
+/// timers are paused while any player has a time freeze effect
 fn expire_pickups(
     time: Res<Time>,
     mut commands: Commands,
     mut game: ResMut<Game>,
-    mut pickups: Query<(&mut AutoRespawn, &BoardPosition, Entity), With<Pickup>>,
+    kinds: Res<PickupKinds>,
+    players: Query<&StatusEffects, With<Player>>,
+    // an expired pickup is only respawned on the next frame, and mustn't cost points twice
+    mut pickups: Query<(&mut AutoRespawn, &Pickup, Entity), Without<Despawn>>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    for (mut timer, _bonus, entity) in pickups.iter_mut() {
+    if players.iter().any(StatusEffects::time_frozen) {
+        return;
+    }
+    for (mut timer, pickup, entity) in pickups.iter_mut() {
         if !timer.0.tick(time.delta()).finished() {
             continue;
         }
-        game.score -= 10;
+        game.score -= kinds
+            .get(pickup.kind)
+            .map(|kind| kind.penalty)
+            .unwrap_or_else(default_penalty);
         commands.entity(entity).insert(Despawn);
+        sfx.send(PlaySfx(Sfx::Expire));
     }
 }
 
@@ -146,15 +298,28 @@ fn rotate_pickups(time: Res<Time>, mut pickups: Query<&mut Transform, With<Picku
 pub fn pickup_collide(
     mut commands: Commands,
     mut game: ResMut<Game>,
-    players: Query<&BoardPosition, With<Player>>,
-    bonus: Query<(&BoardPosition, Entity), (With<Pickup>, (Without<Player>, Without<Despawn>))>,
+    kinds: Res<PickupKinds>,
+    mut players: Query<(&BoardPosition, Option<&mut StatusEffects>), With<Player>>,
+    bonus: Query<(&BoardPosition, &Pickup, Entity), (Without<Player>, Without<Despawn>)>,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    for player_position in players.iter() {
-        for (bonus, entity) in bonus.iter() {
+    for (player_position, mut effects) in players.iter_mut() {
+        for (bonus, pickup, entity) in bonus.iter() {
             if *player_position == *bonus {
-                game.score += 1;
-                game.cake_eaten += 1;
+                let kind = kinds.get(pickup.kind);
+                let score = kind.map(|kind| kind.score).unwrap_or(1);
+                let multiplier = effects.as_ref().map_or(1, |e| e.score_multiplier());
+                game.score += score * multiplier;
+                // power-ups give their effect instead, and don't count towards the cake eaten
+                let effect = kind.and_then(|kind| kind.effect.as_ref());
+                if effect.is_none() {
+                    game.cake_eaten += 1;
+                }
+                if let (Some(effect), Some(effects)) = (effect, effects.as_mut()) {
+                    effects.apply(effect);
+                }
                 commands.entity(entity).insert(Despawn);
+                sfx.send(PlaySfx(Sfx::Collect));
             }
         }
     }
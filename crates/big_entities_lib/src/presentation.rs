@@ -0,0 +1,256 @@
+use crate::{
+    assets::MyEmbeddedAssetsPlugin,
+    audio::{GameAudioPlugin, Sfx, SoundEmitter},
+    chunks::{Cell, ChunkCoord},
+    highscores::{enter_high_score_name, record_high_score, update_high_score_rows},
+    pawn::{Player, PlayerView, Teleported},
+    pickup::{Pickup, PickupKinds},
+    settings::{apply_fps_visibility, apply_msaa, apply_shadows},
+    states::{
+        game_over::display_score, loading::LoadingPlugin, main_menu::MainMenuPlugin,
+        menu::create_menu_ui, GameState,
+    },
+    ui::{
+        button_clicked,
+        fps::{fps_counter_showhide, fps_text_update_system, setup_fps_counter},
+        navigate_buttons,
+        score::{spawn_scoreboard, update_scoreboard},
+        screen::UiScreenPlugin,
+        trigger_check, UiCommandHandlers, UiFocus,
+    },
+    Game, GameplaySet,
+};
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    transform::TransformSystem,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+/// Shows the simulation: attaches models, lights, sound and the camera to its entities,
+/// smooths their movement between gameplay ticks, and puts the ui, menus and loading screen
+/// around it
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MyEmbeddedAssetsPlugin,
+            GameAudioPlugin,
+            LoadingPlugin,
+            MainMenuPlugin,
+            UiScreenPlugin,
+        ))
+        .init_resource::<UiFocus>()
+        .init_resource::<UiCommandHandlers>()
+        .add_systems(
+            // once the simulation's commands are applied, so nothing being attached to
+            // has been despawned in the meantime
+            PostUpdate,
+            (
+                attach_camera,
+                attach_player_models,
+                attach_tile_models,
+                attach_pickup_models,
+            ),
+        )
+        .add_systems(PreUpdate, restore_simulated_transforms)
+        .add_systems(
+            FixedUpdate,
+            record_simulated_transforms.after(GameplaySet::Rules),
+        )
+        .add_systems(
+            PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(
+            Update,
+            (
+                trigger_check,
+                button_clicked,
+                navigate_buttons,
+                spawn_scoreboard,
+                apply_msaa,
+                apply_shadows,
+                apply_fps_visibility,
+            ),
+        )
+        .add_systems(
+            Update,
+            update_scoreboard.run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            display_score.after(record_high_score),
+        )
+        .add_systems(OnEnter(GameState::Menu), (create_menu_ui, release_cursor))
+        .add_systems(
+            Update,
+            update_high_score_rows
+                .after(enter_high_score_name)
+                .run_if(in_state(GameState::GameOver)),
+        );
+
+        #[cfg(debug_assertions)]
+        {
+            app.add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
+            app.add_systems(Startup, setup_fps_counter);
+            app.add_systems(Update, (fps_text_update_system, fps_counter_showhide));
+        }
+    }
+}
+
+/// look through the player's view
+fn attach_camera(mut commands: Commands, views: Query<(Entity, &Transform), Added<PlayerView>>) {
+    for (entity, transform) in views.iter() {
+        commands.entity(entity).insert((
+            Camera3dBundle {
+                transform: *transform,
+                ..default()
+            },
+            Interpolated::new(*transform),
+        ));
+    }
+}
+
+fn attach_player_models(
+    mut commands: Commands,
+    game: Option<Res<Game>>,
+    players: Query<(Entity, &Transform), Added<Player>>,
+) {
+    let Some(game) = game else {
+        return;
+    };
+    for (entity, transform) in players.iter() {
+        commands.entity(entity).insert((
+            game.player_handle.clone(),
+            VisibilityBundle::default(),
+            Interpolated::new(*transform),
+        ));
+    }
+}
+
+/// give tiles their model, and the chunks they are in the visibility their models need
+fn attach_tile_models(
+    mut commands: Commands,
+    game: Option<Res<Game>>,
+    chunks: Query<Entity, Added<ChunkCoord>>,
+    tiles: Query<Entity, Added<Cell>>,
+) {
+    let Some(game) = game else {
+        return;
+    };
+    for entity in chunks.iter() {
+        commands.entity(entity).insert(VisibilityBundle::default());
+    }
+    for entity in tiles.iter() {
+        commands
+            .entity(entity)
+            .insert((game.tile_handle.clone(), VisibilityBundle::default()));
+    }
+}
+
+/// give pickups the model of their kind, a light so they can be seen from afar,
+/// and a hum so they can be heard
+fn attach_pickup_models(
+    mut commands: Commands,
+    kinds: Res<PickupKinds>,
+    pickups: Query<(Entity, &Pickup, &Transform), Added<Pickup>>,
+) {
+    for (entity, Pickup { kind }, transform) in pickups.iter() {
+        let Some(model) = kinds.model(*kind) else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .insert((
+                model.clone(),
+                VisibilityBundle::default(),
+                SoundEmitter(Sfx::Hum),
+                Interpolated::new(*transform),
+            ))
+            .with_children(|children| {
+                children.spawn(PointLightBundle {
+                    point_light: PointLight {
+                        color: Color::rgb(1.0, 1.0, 0.0),
+                        intensity: 1000.0,
+                        range: 10.0,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 2.0, 0.0),
+                    ..default()
+                });
+            });
+    }
+}
+
+/// give the player the mouse back while in the menu
+fn release_cursor(mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.visible = true;
+        window.cursor.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// Shows an entity that gameplay moves in ticks somewhere between where the last two ticks
+/// left it, so it moves smoothly however the ticks fall between frames
+///
+/// its transform is the shown one from after the update until the next frame starts,
+/// and the simulated one otherwise
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Transform,
+    current: Transform,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Interpolated {
+        Interpolated {
+            previous: transform,
+            current: transform,
+        }
+    }
+    /// show the entity where it is now, rather than sliding there from where it was
+    pub fn teleport(&mut self) {
+        self.previous = self.current;
+    }
+}
+
+/// put the simulated transforms back before anything else reads them this frame
+fn restore_simulated_transforms(mut entities: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in entities.iter_mut() {
+        *transform = interpolated.current;
+    }
+}
+
+/// remember where each tick left the entities
+fn record_simulated_transforms(
+    mut teleported: EventReader<Teleported>,
+    mut entities: Query<(&Transform, &mut Interpolated)>,
+) {
+    for (transform, mut interpolated) in entities.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+    }
+    for Teleported { entity } in teleported.read() {
+        if let Ok((_, mut interpolated)) = entities.get_mut(*entity) {
+            interpolated.teleport();
+        }
+    }
+}
+
+/// show the entities part of the way from the last tick to the next, by how much of the next
+/// tick's time has already passed
+fn interpolate_transforms(
+    time: Res<Time<Fixed>>,
+    mut entities: Query<(&mut Transform, &Interpolated)>,
+) {
+    let t = time.overstep_percentage().clamp(0.0, 1.0);
+    for (mut transform, Interpolated { previous, current }) in entities.iter_mut() {
+        *transform = Transform {
+            translation: previous.translation.lerp(current.translation, t),
+            rotation: previous.rotation.slerp(current.rotation, t),
+            scale: previous.scale.lerp(current.scale, t),
+        };
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    actions::{Action, ActionState},
+    chunks::WorldConfiguration,
+    highscores::record_high_score,
+    rng::GameRng,
+    save::{write_data_file, DataDir, PendingLoad},
+    states::{
+        playing::{no_game_in_progress, setup},
+        GameState,
+    },
+    Game, GameplaySet,
+};
+use bevy::{asset::LoadState, prelude::*};
+use std::path::PathBuf;
+
+/// bump this whenever the layout of Replay changes, or gameplay changes so that old replays
+/// would play out differently
+pub const REPLAY_VERSION: u32 = 6;
+const REPLAY_FILE: &str = "replay.ron";
+/// environment variable that can be used to watch another replay file than the last game's
+pub const REPLAY_VAR: &str = "BIG_ENTITIES_REPLAY";
+
+/// Records the input of every new game to the replay file, and plays replays back
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecording>()
+            .init_resource::<LastReplay>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                start_recording.before(setup).run_if(no_game_in_progress),
+            )
+            .add_systems(
+                Update,
+                start_loaded_replay.run_if(resource_exists::<PendingReplay>()),
+            )
+            // a game started while a replay's world loads takes the replay's place
+            .add_systems(OnEnter(GameState::Playing), forget_pending_replay)
+            .add_systems(
+                FixedUpdate,
+                (
+                    record_tick,
+                    play_tick.run_if(resource_exists::<ReplayPlayback>()),
+                )
+                    .in_set(GameplaySet::Input),
+            )
+            .add_systems(
+                FixedUpdate,
+                leave_replay
+                    .after(GameplaySet::Rules)
+                    .run_if(replay_played_out),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                // a replay's game isn't a new high score
+                (finish_recording, finish_playback.after(record_high_score)),
+            )
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (finish_recording, finish_playback),
+            );
+    }
+}
+
+/// A game's seed and input, which plays out the same way again when fed back in
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    /// asset path of the world the game was played in, if it came from a file
+    pub world: Option<String>,
+    /// the input of each gameplay tick
+    pub ticks: Vec<ActionState>,
+    /// how the game ended, to check the replay against
+    pub score: i32,
+    pub cake_eaten: u32,
+}
+
+/// Just the version of a replay file, read before the rest so mismatched replays can be refused
+#[derive(serde::Deserialize)]
+struct ReplayVersion {
+    version: u32,
+}
+
+impl Replay {
+    /// the replay file to watch, from the replay environment variable,
+    /// or else the last game's in the data directory
+    pub fn path(dir: &DataDir) -> Option<PathBuf> {
+        std::env::var_os(REPLAY_VAR)
+            .map(PathBuf::from)
+            .or_else(|| dir.file(REPLAY_FILE))
+    }
+    /// write this replay over the last game's replay file
+    pub fn save(&self, dir: &DataDir) -> Result<(), String> {
+        let path = dir
+            .file(REPLAY_FILE)
+            .ok_or("no data directory on this platform")?;
+        let text =
+            ron::to_string(self).map_err(|e| format!("could not serialize the replay: {e}"))?;
+        write_data_file(&path, &text)
+    }
+    /// read the replay file, refusing replays from other versions of the game
+    pub fn load(dir: &DataDir) -> Result<Replay, String> {
+        let path = Replay::path(dir).ok_or("no data directory on this platform")?;
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        let ReplayVersion { version } =
+            ron::from_str(&text).map_err(|e| format!("replay file is not valid: {e}"))?;
+        if version != REPLAY_VERSION {
+            return Err(format!(
+                "replay file is version {version}, but this game reads version {REPLAY_VERSION}"
+            ));
+        }
+        ron::from_str(&text).map_err(|e| format!("replay file is not valid: {e}"))
+    }
+}
+
+/// The replay of the game being played, if it started as a new game
+#[derive(Resource, Default)]
+pub struct ReplayRecording(pub Option<Replay>);
+
+/// The replay of the last game that ended
+#[derive(Resource, Default)]
+pub struct LastReplay(pub Option<Replay>);
+
+/// A replay being played back, which feeds its input to the game tick by tick
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    /// index of the tick to play next
+    next: usize,
+    /// the world new games were played in before the replay, to go back to after it
+    previous_world: Handle<WorldConfiguration>,
+}
+
+/// A replay waiting for its world to load before it can start
+#[derive(Resource)]
+struct PendingReplay {
+    replay: Replay,
+    world: Handle<WorldConfiguration>,
+}
+
+impl ReplayPlayback {
+    /// every tick of the replay has been played
+    pub fn played_out(&self) -> bool {
+        self.next >= self.replay.ticks.len()
+    }
+}
+
+/// start recording a new game, but not a loaded one or a replay, which don't start fresh
+fn start_recording(
+    game: Res<Game>,
+    pending: Res<PendingLoad>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    recording.0 = (pending.0.is_none() && playback.is_none()).then(|| Replay {
+        version: REPLAY_VERSION,
+        // the seed is chosen as the game is set up, so it is filled in at the end
+        seed: 0,
+        world: game.world_handle.path().map(|path| path.to_string()),
+        ticks: Vec::new(),
+        score: 0,
+        cake_eaten: 0,
+    });
+}
+
+/// record this tick's input, leaving out pausing, which isn't part of the game
+fn record_tick(actions: Res<ActionState>, mut recording: ResMut<ReplayRecording>) {
+    let Some(replay) = recording.0.as_mut() else {
+        return;
+    };
+    let mut input = actions.clone();
+    input.pressed.retain(|action| *action != Action::Pause);
+    input.just_pressed.retain(|action| *action != Action::Pause);
+    replay.ticks.push(input);
+}
+
+/// write the replay of the game that just ended to the replay file
+fn finish_recording(
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    dir: Res<DataDir>,
+    mut recording: ResMut<ReplayRecording>,
+    mut last: ResMut<LastReplay>,
+) {
+    let Some(mut replay) = recording.0.take() else {
+        return;
+    };
+    replay.seed = rng.seed();
+    replay.score = game.score;
+    replay.cake_eaten = game.cake_eaten;
+    match replay.save(&dir) {
+        Ok(()) => info!("recorded {} ticks to the replay file", replay.ticks.len()),
+        Err(e) => warn!("could not save the replay: {e}"),
+    }
+    last.0 = Some(replay);
+}
+
+/// play a replay, starting a new game with its seed and world,
+/// once the world has loaded if it isn't already
+///
+/// should be started from outside a game, such as from the main menu
+pub fn start_playback(world: &mut World, replay: Replay) {
+    if replay.ticks.is_empty() {
+        warn!("the replay has no ticks to play");
+        return;
+    }
+    let handle = match &replay.world {
+        Some(path) => world.resource::<AssetServer>().load(path.clone()),
+        None => world.resource::<Game>().world_handle.clone(),
+    };
+    if world
+        .resource::<Assets<WorldConfiguration>>()
+        .contains(&handle)
+    {
+        begin_playback(world, replay, handle);
+    } else {
+        world.insert_resource(PendingReplay {
+            replay,
+            world: handle,
+        });
+    }
+}
+
+/// start a new game playing the replay in its world, which has loaded
+fn begin_playback(world: &mut World, replay: Replay, handle: Handle<WorldConfiguration>) {
+    let previous_world = std::mem::replace(&mut world.resource_mut::<Game>().world_handle, handle);
+    world.resource_mut::<PendingLoad>().0 = None;
+    world.insert_resource(ReplayPlayback {
+        replay,
+        next: 0,
+        previous_world,
+    });
+    world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+}
+
+/// start the replay waiting on its world once the world has loaded,
+/// or refuse it if the world can't be loaded
+fn start_loaded_replay(world: &mut World) {
+    let Some(pending) = world.get_resource::<PendingReplay>() else {
+        return;
+    };
+    let loaded = world
+        .resource::<Assets<WorldConfiguration>>()
+        .contains(&pending.world);
+    let failed = world
+        .resource::<AssetServer>()
+        .get_load_state(&pending.world)
+        == Some(LoadState::Failed);
+    if !loaded && !failed {
+        return;
+    }
+    let Some(pending) = world.remove_resource::<PendingReplay>() else {
+        return;
+    };
+    if loaded {
+        begin_playback(world, pending.replay, pending.world);
+    } else {
+        warn!(
+            "could not watch the replay: its world {} can't be loaded",
+            pending.replay.world.as_deref().unwrap_or("unknown")
+        );
+    }
+}
+
+fn forget_pending_replay(mut commands: Commands) {
+    commands.remove_resource::<PendingReplay>();
+}
+
+/// play the replay file
+pub fn watch_replay(world: &mut World) {
+    match Replay::load(world.resource::<DataDir>()) {
+        Ok(replay) => start_playback(world, replay),
+        Err(e) => warn!("could not watch the replay: {e}"),
+    }
+}
+
+/// feed the replay's input for this tick to the game, in place of the player's,
+/// who can still pause it
+fn play_tick(mut playback: ResMut<ReplayPlayback>, mut actions: ResMut<ActionState>) {
+    let Some(input) = playback.replay.ticks.get(playback.next) else {
+        return;
+    };
+    let live = std::mem::replace(&mut *actions, input.clone());
+    if live.pressed.contains(&Action::Pause) {
+        actions.pressed.push(Action::Pause);
+    }
+    if live.just_pressed.contains(&Action::Pause) {
+        actions.just_pressed.push(Action::Pause);
+    }
+    playback.next += 1;
+}
+
+fn replay_played_out(playback: Option<Res<ReplayPlayback>>) -> bool {
+    playback.is_some_and(|playback| playback.played_out())
+}
+
+/// go back to the title screen once the whole replay has played,
+/// unless its game ended by itself on the last tick
+fn leave_replay(mut next_state: ResMut<NextState<GameState>>) {
+    if next_state.0.is_none() {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+/// stop playing the replay, reporting whether it ended the way the recorded game did,
+/// and go back to the world new games were played in before it
+fn finish_playback(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    mut game: ResMut<Game>,
+) {
+    let Some(playback) = playback else {
+        return;
+    };
+    let replay = &playback.replay;
+    if !playback.played_out() {
+        info!(
+            "stopped the replay after {} of its {} ticks",
+            playback.next,
+            replay.ticks.len()
+        );
+    } else if (game.score, game.cake_eaten) == (replay.score, replay.cake_eaten) {
+        info!("the replay played out as recorded, scoring {}", game.score);
+    } else {
+        warn!(
+            "the replay scored {} with {} cake eaten, but the recorded game scored {} with {}",
+            game.score, game.cake_eaten, replay.score, replay.cake_eaten
+        );
+    }
+    game.world_handle = playback.previous_world.clone();
+    commands.remove_resource::<ReplayPlayback>();
+}
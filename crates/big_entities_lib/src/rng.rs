@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// environment variable that can be used to pick the seed
+pub const SEED_VAR: &str = "BIG_ENTITIES_SEED";
+/// command line argument that can be used to pick the seed, as `--seed 1234` or `--seed=1234`
+pub const SEED_ARG: &str = "--seed";
+
+/// Seed requested from outside the game, takes priority over the seed in the world configuration
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct SeedOverride(pub Option<u64>);
+
+impl SeedOverride {
+    /// read the seed from the command line, or failing that the environment
+    pub fn from_env() -> SeedOverride {
+        let mut args = std::env::args();
+        let from_args = loop {
+            let Some(arg) = args.next() else {
+                break None;
+            };
+            if arg == SEED_ARG {
+                break args.next();
+            }
+            if let Some(value) = arg.strip_prefix(SEED_ARG).and_then(|a| a.strip_prefix('=')) {
+                break Some(value.to_string());
+            }
+        };
+        let seed = from_args
+            .or_else(|| std::env::var(SEED_VAR).ok())
+            .and_then(|value| match value.trim().parse() {
+                Ok(seed) => Some(seed),
+                Err(_) => {
+                    warn!("ignoring invalid seed {value:?}");
+                    None
+                }
+            });
+        SeedOverride(seed)
+    }
+}
+
+/// The source of all randomness in a game, so the same seed plays out the same way
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    /// the algorithm behind `StdRng`, used directly since it can say how far along it is
+    rng: ChaCha12Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        GameRng {
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+    /// seed from the current time
+    pub fn from_clock() -> GameRng {
+        GameRng::new(clock_seed())
+    }
+    /// the seed this rng was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// how many words have been drawn since seeding, so a saved game can carry on from there
+    pub fn position(&self) -> u64 {
+        self.rng.get_word_pos() as u64
+    }
+    /// pick up drawing where an rng with the same seed was at this position
+    pub fn set_position(&mut self, position: u64) {
+        self.rng.set_word_pos(position.into());
+    }
+    /// an rng that's always the same for this tile and seed,
+    /// so chunks that are streamed back in regenerate identically
+    pub fn for_tile(&self, x: usize, y: usize) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ ((x as u64) << 32 | y as u64))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::from_clock()
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// the system clock isn't available on wasm, so fall back to the os rng
+#[cfg(target_arch = "wasm32")]
+fn clock_seed() -> u64 {
+    use rand::Rng;
+    rand::thread_rng().gen()
+}
+
+/// pick the seed for a new game: an override wins, then the world's own seed, then the clock
+pub fn choose_seed(seed_override: &SeedOverride, world_seed: Option<u64>) -> u64 {
+    seed_override.0.or(world_seed).unwrap_or_else(clock_seed)
+}
@@ -0,0 +1,305 @@
+use crate::{
+    chunks::{BoardPosition, ChunkCoord, ChunkData, ChunkMap, WorldConfiguration},
+    effects::{ActiveEffect, EffectKind, StatusEffects},
+    pawn::{
+        control_mode::ControlMode, free_movement::FirstPersonPawn, gravity::VerticalMotion, Player,
+    },
+    pickup::{AutoRespawn, Despawn, Pickup, PickupKinds},
+    rng::GameRng,
+    states::GameState,
+    Game,
+};
+use bevy::prelude::*;
+use std::{path::PathBuf, time::Duration};
+
+/// bump this whenever the layout of SaveGame changes, so old saves are refused instead of misread
+pub const SAVE_VERSION: u32 = 4;
+const SAVE_FILE: &str = "save.ron";
+
+/// Keeps a snapshot of the game while it is paused, so it can be saved from the menu
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSnapshot>()
+            .init_resource::<PendingLoad>()
+            .add_systems(OnExit(GameState::Playing), snapshot_game)
+            .add_systems(OnEnter(GameState::GameOver), discard_snapshot);
+    }
+}
+
+/// environment variable that can be used to keep the game's files in another folder
+pub const DATA_DIR_VAR: &str = "BIG_ENTITIES_DATA_DIR";
+
+/// The folder the game's files are kept in, if the platform has one
+#[derive(Resource, Clone, Debug)]
+pub struct DataDir(pub Option<PathBuf>);
+
+impl DataDir {
+    /// the folder given by the data directory environment variable,
+    /// or else this game's folder of the platform data directory
+    pub fn from_env() -> DataDir {
+        let dir = std::env::var_os(DATA_DIR_VAR)
+            .map(PathBuf::from)
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("big_entities")));
+        DataDir(dir)
+    }
+    /// the path of a file in the folder
+    pub fn file(&self, name: &str) -> Option<PathBuf> {
+        self.0.as_ref().map(|dir| dir.join(name))
+    }
+}
+
+/// write a file in the data directory, creating the directory if needed
+pub fn write_data_file(path: &PathBuf, text: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("could not create {}: {e}", dir.display()))?;
+    }
+    std::fs::write(path, text).map_err(|e| format!("could not write {}: {e}", path.display()))
+}
+
+/// The player's pawn in a saved game
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SavedPlayer {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub position: BoardPosition,
+    pub control_mode: ControlMode,
+    /// the first person camera's pitch, if the pawn was in first person
+    pub look_rotation: Option<f32>,
+    /// mid-jump or mid-fall, the pawn carries on moving the same way
+    pub motion: VerticalMotion,
+    pub effects: Vec<SavedEffect>,
+}
+
+/// A status effect on the player in a saved game
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SavedEffect {
+    pub kind: EffectKind,
+    /// seconds the effect lasts in all, which extending it may have made longer
+    pub duration: f32,
+    /// seconds left before it wears off
+    pub remaining: f32,
+}
+
+impl SavedEffect {
+    fn new(active: &ActiveEffect) -> SavedEffect {
+        SavedEffect {
+            kind: active.kind,
+            duration: active.timer.duration().as_secs_f32(),
+            remaining: active.remaining(),
+        }
+    }
+    /// the effect as it was when the game was saved
+    pub fn restore(&self) -> ActiveEffect {
+        let mut timer = Timer::from_seconds(self.duration, TimerMode::Once);
+        timer.set_elapsed(Duration::from_secs_f32(
+            (self.duration - self.remaining).max(0.0),
+        ));
+        ActiveEffect {
+            kind: self.kind,
+            timer,
+        }
+    }
+}
+
+/// The tile heights of one chunk in a saved game, in row order
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SavedChunk {
+    pub coord: ChunkCoord,
+    pub heights: Vec<f32>,
+}
+
+/// A pickup in a saved game
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SavedPickup {
+    /// name of its kind, which stays the same when the pickups file is reordered
+    pub kind: String,
+    pub position: BoardPosition,
+    pub translation: Vec3,
+    /// seconds left before it expires
+    pub remaining: f32,
+}
+
+/// An in-progress game, as written to the save file
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct SaveGame {
+    pub version: u32,
+    /// asset path of the world the game was played in
+    pub world: String,
+    pub seed: u64,
+    /// how far the game's rng had got, so the game carries on as it would have
+    pub rng_position: u64,
+    pub score: i32,
+    pub cake_eaten: u32,
+    pub play_time: f32,
+    pub player: SavedPlayer,
+    pub chunks: Vec<SavedChunk>,
+    pub pickups: Vec<SavedPickup>,
+}
+
+/// Just the version of a save file, read before the rest so mismatched saves can be refused
+#[derive(serde::Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+impl SaveGame {
+    /// write this game to the save file
+    pub fn save(&self, dir: &DataDir) -> Result<(), String> {
+        let path = dir
+            .file(SAVE_FILE)
+            .ok_or("no data directory on this platform")?;
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| format!("could not serialize the game: {e}"))?;
+        write_data_file(&path, &text)
+    }
+    /// read the save file, refusing saves from other versions of the game
+    pub fn load(dir: &DataDir) -> Result<SaveGame, String> {
+        let path = dir
+            .file(SAVE_FILE)
+            .ok_or("no data directory on this platform")?;
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+        let SaveVersion { version } =
+            ron::from_str(&text).map_err(|e| format!("save file is not valid: {e}"))?;
+        if version != SAVE_VERSION {
+            return Err(format!(
+                "save file is version {version}, but this game reads version {SAVE_VERSION}"
+            ));
+        }
+        ron::from_str(&text).map_err(|e| format!("save file is not valid: {e}"))
+    }
+}
+
+/// The game as it was when play last stopped, while it can still be resumed
+#[derive(Resource, Default)]
+pub struct GameSnapshot(pub Option<SaveGame>);
+
+/// A saved game to restore the next time play starts, instead of starting a new game
+#[derive(Resource, Default)]
+pub struct PendingLoad(pub Option<SaveGame>);
+
+/// record the game as play stops, so it can be saved from the menu
+pub fn snapshot_game(
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    map: Res<ChunkMap>,
+    kinds: Res<PickupKinds>,
+    mut snapshot: ResMut<GameSnapshot>,
+    players: Query<
+        (
+            &Transform,
+            &BoardPosition,
+            &ControlMode,
+            Option<&FirstPersonPawn>,
+            &VerticalMotion,
+            &StatusEffects,
+        ),
+        With<Player>,
+    >,
+    chunks: Query<&ChunkData>,
+    pickups: Query<(&Pickup, &BoardPosition, &Transform, &AutoRespawn), Without<Despawn>>,
+) {
+    let Ok((transform, position, control_mode, pawn, motion, effects)) = players.get_single()
+    else {
+        snapshot.0 = None;
+        return;
+    };
+    // a world that didn't come from a file couldn't be found again to load the save into
+    let Some(world) = game.world_handle.path() else {
+        snapshot.0 = None;
+        return;
+    };
+    let loaded = map.loaded().filter_map(|(coord, entity)| {
+        chunks.get(entity).ok().map(|chunk| SavedChunk {
+            coord,
+            heights: chunk.heights(),
+        })
+    });
+    let unloaded = map.saved_heights().map(|(coord, heights)| SavedChunk {
+        coord,
+        heights: heights.to_vec(),
+    });
+    snapshot.0 = Some(SaveGame {
+        version: SAVE_VERSION,
+        world: world.to_string(),
+        seed: rng.seed(),
+        rng_position: rng.position(),
+        score: game.score,
+        cake_eaten: game.cake_eaten,
+        play_time: game.play_time,
+        player: SavedPlayer {
+            translation: transform.translation,
+            rotation: transform.rotation,
+            position: *position,
+            control_mode: *control_mode,
+            look_rotation: pawn.map(|pawn| pawn.look_rotation),
+            motion: motion.clone(),
+            effects: effects.iter().map(SavedEffect::new).collect(),
+        },
+        chunks: loaded.chain(unloaded).collect(),
+        pickups: pickups
+            .iter()
+            .filter_map(|(pickup, position, transform, timer)| {
+                Some(SavedPickup {
+                    kind: kinds.get(pickup.kind)?.name.clone(),
+                    position: *position,
+                    translation: transform.translation,
+                    remaining: timer.0.remaining_secs(),
+                })
+            })
+            .collect(),
+    });
+}
+
+/// a finished game can't be saved
+fn discard_snapshot(mut snapshot: ResMut<GameSnapshot>) {
+    snapshot.0 = None;
+}
+
+/// write the snapshot of the paused game to the save file
+pub fn save_snapshot(world: &mut World) {
+    let Some(save) = world
+        .get_resource::<GameSnapshot>()
+        .and_then(|snapshot| snapshot.0.as_ref())
+    else {
+        warn!("there is no game in progress to save");
+        return;
+    };
+    match save.save(world.resource::<DataDir>()) {
+        Ok(()) => info!("saved the game"),
+        Err(e) => warn!("could not save the game: {e}"),
+    }
+}
+
+/// read the save file, so the next game starts from it in the world it was saved in
+///
+/// returns false, leaving the next game to start fresh, if the save can't be used
+pub fn load_save(world: &mut World) -> bool {
+    let save = match SaveGame::load(world.resource::<DataDir>()) {
+        Ok(save) => save,
+        Err(e) => {
+            warn!("could not load the saved game: {e}");
+            return false;
+        }
+    };
+    let handle: Handle<WorldConfiguration> =
+        world.resource::<AssetServer>().load(save.world.clone());
+    // the game is set up as soon as play starts, so the world has to be loaded already,
+    // as every world in the worlds folder is
+    if !world
+        .resource::<Assets<WorldConfiguration>>()
+        .contains(&handle)
+    {
+        warn!(
+            "could not load the saved game: its world {} isn't loaded",
+            save.world
+        );
+        return false;
+    }
+    world.resource_mut::<Game>().world_handle = handle;
+    world.insert_resource(PendingLoad(Some(save)));
+    true
+}
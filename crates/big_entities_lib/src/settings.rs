@@ -0,0 +1,198 @@
+use crate::{
+    save::{write_data_file, DataDir},
+    ui::fps::FpsRoot,
+};
+use bevy::prelude::*;
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+/// Loads the player's settings
+///
+/// the mouse sensitivity applies as the mouse is read, and the graphics settings are applied
+/// by the presentation whenever they change
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = Settings::load(app.world.resource::<DataDir>());
+        app.insert_resource(settings);
+    }
+}
+
+/// Options the player can change, saved as RON in the platform data directory
+#[derive(serde::Deserialize, serde::Serialize, Resource, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// scales how fast the mouse turns the first person pawn
+    pub mouse_sensitivity: f32,
+    /// closest the follow camera can be zoomed in
+    pub zoom_min: f32,
+    /// furthest the follow camera can be zoomed out
+    pub zoom_max: f32,
+    /// smooth jagged edges with multisampling
+    pub msaa: bool,
+    /// lights cast shadows
+    pub shadows: bool,
+    pub show_fps: bool,
+    /// from 0.0 for silent to 1.0 for full volume
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mouse_sensitivity: 1.0,
+            zoom_min: 3.0,
+            zoom_max: 100.0,
+            msaa: false,
+            shadows: true,
+            show_fps: true,
+            music_volume: 0.5,
+            sfx_volume: 0.8,
+        }
+    }
+}
+
+/// One of the settings, for menus to refer to
+#[derive(serde::Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Setting {
+    MouseSensitivity,
+    ZoomMin,
+    ZoomMax,
+    Msaa,
+    Shadows,
+    ShowFps,
+    MusicVolume,
+    SfxVolume,
+}
+
+impl Settings {
+    /// read the settings file, falling back to the defaults if there isn't a usable one
+    pub fn load(dir: &DataDir) -> Settings {
+        let Some(path) = dir.file(SETTINGS_FILE) else {
+            return Settings::default();
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Settings::default();
+        };
+        match ron::from_str(&text) {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("could not read settings from {}: {e}", path.display());
+                Settings::default()
+            }
+        }
+    }
+    /// write the settings file
+    pub fn save(&self, dir: &DataDir) {
+        let Some(path) = dir.file(SETTINGS_FILE) else {
+            return;
+        };
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("could not serialize settings: {e}");
+                return;
+            }
+        };
+        if let Err(e) = write_data_file(&path, &text) {
+            warn!("could not save settings: {e}");
+        }
+    }
+    pub fn msaa(&self) -> Msaa {
+        if self.msaa {
+            Msaa::Sample4
+        } else {
+            Msaa::Off
+        }
+    }
+    /// the follow camera's zoom limits, in order even if they were set the wrong way round
+    pub fn zoom_range(&self) -> (f32, f32) {
+        (
+            self.zoom_min.min(self.zoom_max),
+            self.zoom_min.max(self.zoom_max),
+        )
+    }
+    /// step a setting up or down, or switch it on or off
+    pub fn change(&mut self, setting: Setting, steps: i32) {
+        let steps = steps as f32;
+        match setting {
+            Setting::MouseSensitivity => {
+                self.mouse_sensitivity = (self.mouse_sensitivity + steps * 0.1).clamp(0.1, 5.0)
+            }
+            // each zoom limit stays on its own side of the other
+            Setting::ZoomMin => self.zoom_min = (self.zoom_min + steps).min(self.zoom_max).max(1.0),
+            Setting::ZoomMax => {
+                self.zoom_max = (self.zoom_max + steps * 10.0).max(self.zoom_min).min(200.0)
+            }
+            Setting::Msaa => self.msaa = !self.msaa,
+            Setting::Shadows => self.shadows = !self.shadows,
+            Setting::ShowFps => self.show_fps = !self.show_fps,
+            Setting::MusicVolume => {
+                self.music_volume = (self.music_volume + steps * 0.1).clamp(0.0, 1.0)
+            }
+            Setting::SfxVolume => self.sfx_volume = (self.sfx_volume + steps * 0.1).clamp(0.0, 1.0),
+        }
+    }
+    /// a setting's name and value, for menus
+    pub fn describe(&self, setting: Setting) -> String {
+        fn on_off(on: bool) -> &'static str {
+            if on {
+                "on"
+            } else {
+                "off"
+            }
+        }
+        match setting {
+            Setting::MouseSensitivity => {
+                format!("Mouse sensitivity: {:.1}", self.mouse_sensitivity)
+            }
+            Setting::ZoomMin => format!("Closest zoom: {:.0}", self.zoom_min),
+            Setting::ZoomMax => format!("Furthest zoom: {:.0}", self.zoom_max),
+            Setting::Msaa => format!("MSAA: {}", on_off(self.msaa)),
+            Setting::Shadows => format!("Shadows: {}", on_off(self.shadows)),
+            Setting::ShowFps => format!("FPS: {}", on_off(self.show_fps)),
+            Setting::MusicVolume => format!("Music: {:.0}%", self.music_volume * 100.0),
+            Setting::SfxVolume => format!("Sounds: {:.0}%", self.sfx_volume * 100.0),
+        }
+    }
+}
+
+/// change a setting and save the settings file
+pub fn change_setting(world: &mut World, setting: Setting, steps: i32) {
+    let dir = world.resource::<DataDir>().clone();
+    let mut settings = world.resource_mut::<Settings>();
+    settings.change(setting, steps);
+    settings.save(&dir);
+}
+
+pub fn apply_msaa(settings: Res<Settings>, mut msaa: ResMut<Msaa>) {
+    if settings.is_changed() {
+        *msaa = settings.msaa();
+    }
+}
+
+pub fn apply_shadows(settings: Res<Settings>, mut lights: Query<&mut PointLight>) {
+    for mut light in lights.iter_mut() {
+        if (settings.is_changed() || light.is_added()) && light.shadows_enabled != settings.shadows
+        {
+            light.shadows_enabled = settings.shadows;
+        }
+    }
+}
+
+pub fn apply_fps_visibility(
+    settings: Res<Settings>,
+    mut counters: Query<(&mut Visibility, Ref<FpsRoot>)>,
+) {
+    for (mut visibility, root) in counters.iter_mut() {
+        if settings.is_changed() || root.is_added() {
+            *visibility = if settings.show_fps {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
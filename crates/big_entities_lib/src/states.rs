@@ -2,14 +2,68 @@ use bevy::prelude::*;
 
 pub mod game_over;
 pub mod loading;
+pub mod main_menu;
 pub mod menu;
 pub mod playing;
+pub mod settings;
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Default, States, serde::Deserialize)]
 pub enum GameState {
     #[default]
     Loading,
+    /// the title screen, where new games are started
+    MainMenu,
     Playing,
     GameOver,
+    /// the pause menu
     Menu,
+    Settings,
+    Credits,
+}
+
+impl GameState {
+    pub const ALL: [GameState; 7] = [
+        GameState::Loading,
+        GameState::MainMenu,
+        GameState::Playing,
+        GameState::GameOver,
+        GameState::Menu,
+        GameState::Settings,
+        GameState::Credits,
+    ];
+}
+
+/// Entities with this component are despawned, with their children, when their scope ends
+#[derive(Component, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Scope {
+    /// belongs to one state, such as that state's ui, and is despawned when leaving it
+    State(GameState),
+    /// belongs to the game being played, kept while paused and despawned when the game ends
+    Game,
+}
+
+/// a system that despawns the entities of the given scope
+pub fn despawn_scope(scope: Scope) -> impl FnMut(Commands, Query<(Entity, &Scope)>) {
+    move |mut commands, entities| {
+        for (entity, entity_scope) in entities.iter() {
+            if *entity_scope == scope {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// despawn the entities of the given scope right away, such as the game before loading another one
+pub fn despawn_scope_now(world: &mut World, scope: Scope) {
+    let entities = world
+        .query::<(Entity, &Scope)>()
+        .iter(world)
+        .filter(|(_, entity_scope)| **entity_scope == scope)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    for entity in entities {
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+    }
 }
@@ -1,29 +1,51 @@
-use super::GameState;
-use crate::Game;
+use super::{GameState, Scope};
+use crate::{
+    actions::{Action, Actions},
+    highscores::{high_score_text, HighScoreRow, HighScores, LatestHighScore},
+    rng::GameRng,
+    Game,
+};
 use bevy::prelude::*;
 
 /// process keyboard input during game over state
 pub fn gameover_keyboard(
     mut next_state: ResMut<NextState<GameState>>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Actions,
+    latest: Res<LatestHighScore>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    // confirm finishes typing a name before it starts a new game
+    if latest.entering_name {
+        return;
+    }
+    if actions.just_pressed(Action::Confirm) {
         next_state.set(GameState::Playing);
+    } else if actions.just_pressed(Action::Back) {
+        next_state.set(GameState::MainMenu);
     }
 }
 
 /// add score display to screen during gameend
-pub fn display_score(mut commands: Commands, game: Res<Game>) {
+pub fn display_score(
+    mut commands: Commands,
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    scores: Res<HighScores>,
+    latest: Res<LatestHighScore>,
+) {
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(100.),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
+        .spawn((
+            Scope::State(GameState::GameOver),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+        ))
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
                 format!("Cake eaten: {}", game.cake_eaten),
@@ -33,5 +55,41 @@ pub fn display_score(mut commands: Commands, game: Res<Game>) {
                     ..default()
                 },
             ));
+            parent.spawn(TextBundle::from_section(
+                format!("Seed: {}", rng.seed()),
+                TextStyle {
+                    font_size: 30.0,
+                    color: Color::rgb(0.5, 0.5, 1.0),
+                    ..default()
+                },
+            ));
+            if latest.entering_name {
+                parent.spawn(TextBundle::from_section(
+                    "New high score! Enter your name",
+                    TextStyle {
+                        font_size: 30.0,
+                        color: Color::rgb(1.0, 0.8, 0.3),
+                        ..default()
+                    },
+                ));
+            }
+            for (rank, entry) in scores.entries.iter().enumerate() {
+                let latest_entry = latest.rank == Some(rank);
+                parent.spawn((
+                    HighScoreRow(rank),
+                    TextBundle::from_section(
+                        high_score_text(rank, entry, latest_entry && latest.entering_name),
+                        TextStyle {
+                            font_size: 24.0,
+                            color: if latest_entry {
+                                Color::rgb(1.0, 0.8, 0.3)
+                            } else {
+                                Color::rgb(0.5, 0.5, 1.0)
+                            },
+                            ..default()
+                        },
+                    ),
+                ));
+            }
         });
 }
@@ -1,31 +1,102 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{LoadState, UntypedAssetId, UntypedHandle},
+    ecs::system::CommandQueue,
+    prelude::*,
+};
 
 use crate::{
-    chunks::{WorldConfiguration, RESET_FOCUS},
-    ui::{Trigger, UiCommand, UiCommands},
+    audio::SoundEffects,
+    chunks::WorldConfiguration,
+    pickup::{PickupCatalog, PickupKinds},
+    ui::{
+        screen::{ProgressBar, UiScreen, UiScreenRoot},
+        spawn_button, spawn_heading, spawn_screen, trigger_check, AddUiCommand, BackButton,
+        Trigger, TriggerCondition, UiCommand, UiCommands,
+    },
     Game,
 };
 
-use super::GameState;
+use super::{despawn_scope_now, GameState, Scope};
 
 const TILE_PATH: &str = "embedded://big_entities_lib/assets/models/AlienCake/tile.glb#Scene0";
 const PLAYER_PATH: &str = "embedded://big_entities_lib/assets/models/AlienCake/alien.glb#Scene0";
 const PICKUP_PATH: &str =
     "embedded://big_entities_lib/assets/models/AlienCake/cakeBirthday.glb#Scene0";
 const WORLD_PATH: &str = "worlds/main.world.ron";
+const PICKUPS_PATH: &str = "food.pickups.ron";
+const LOADING_UI_PATH: &str = "ui/loading.ui.ron";
+/// names of the ui commands that the loading screens use
+const SHOW_LOAD_FAILURE: &str = "show_load_failure";
+const RETRY_LOADING: &str = "retry_loading";
+/// seconds to wait for a retried asset to start loading before its failure counts again
+const RETRY_GRACE: f32 = 1.0;
 
-/// set up the camera, light, and loading ui's, as well as initiate model asset loading
-pub fn setup_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(Camera3dBundle {
-        transform: Transform::from_xyz(
-            -(4.0 as f32 / 2.0),
-            2.0 * 4.0 as f32 / 3.0,
-            4.0 as f32 / 2.0 - 0.5,
-        )
-        .looking_at(Vec3::from(RESET_FOCUS), Vec3::Y),
-        ..default()
-    });
+/// Loads the game's assets, showing their progress, and what failed to load
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Loading), setup_loading)
+            .add_systems(
+                Update,
+                (
+                    update_loading_progress,
+                    watch_retried_assets,
+                    wait_for_catalog_models.before(trigger_check),
+                )
+                    .run_if(in_state(GameState::Loading)),
+            )
+            .add_ui_command(SHOW_LOAD_FAILURE, |world, _| show_load_failure(world))
+            .add_ui_command(RETRY_LOADING, |world, _| retry_loading(world));
+    }
+}
+
+/// Every asset that must load before the title screen is shown
+///
+/// the models named in the pickups file are added once it has loaded
+#[derive(Resource)]
+pub struct LoadingAssets(pub Vec<UntypedHandle>);
+
+/// Marks the triggers that wait on the loading assets, so they can be told of more
+#[derive(Component)]
+struct LoadingTrigger;
 
+impl LoadingAssets {
+    /// how many of the assets have loaded, out of how many
+    pub fn progress(&self, asset_server: &AssetServer) -> (usize, usize) {
+        let loaded = self
+            .0
+            .iter()
+            .filter(|handle| asset_server.get_load_state(handle.id()) == Some(LoadState::Loaded))
+            .count();
+        (loaded, self.0.len())
+    }
+    pub fn failed<'a>(
+        &'a self,
+        asset_server: &'a AssetServer,
+    ) -> impl Iterator<Item = &'a UntypedHandle> {
+        self.0
+            .iter()
+            .filter(|handle| asset_server.get_load_state(handle.id()) == Some(LoadState::Failed))
+    }
+}
+
+/// The assets that failed and are being loaded again
+///
+/// they only start loading once the asset server gets to them, so until then they still
+/// report their old failure, which mustn't bring the failure screen straight back
+#[derive(Resource)]
+struct RetriedAssets {
+    ids: Vec<UntypedAssetId>,
+    grace: Timer,
+}
+
+/// set up the light and loading ui's, as well as initiate model asset loading
+pub fn setup_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    sounds: Res<SoundEffects>,
+) {
     commands.spawn(PointLightBundle {
         transform: Transform::from_xyz(4.0, 10.0, 4.0),
         point_light: PointLight {
@@ -41,103 +112,202 @@ pub fn setup_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
     let player_handle: Handle<Scene> = asset_server.load(PLAYER_PATH);
     let pickup_handle: Handle<Scene> = asset_server.load(PICKUP_PATH);
     let world_handle: Handle<WorldConfiguration> = asset_server.load(WORLD_PATH);
+    let pickups_handle: Handle<PickupCatalog> = asset_server.load(PICKUPS_PATH);
 
-    create_ui(
-        &mut commands,
-        vec![
-            tile_handle.clone(),
-            player_handle.clone(),
-            pickup_handle.clone(),
-        ],
-    );
+    // the world is needed as soon as a game starts, so it is waited for along with the models
+    let mut assets = LoadingAssets(vec![
+        tile_handle.clone().untyped(),
+        player_handle.clone().untyped(),
+        pickup_handle.clone().untyped(),
+        world_handle.clone().untyped(),
+        pickups_handle.clone().untyped(),
+        asset_server.load::<UiScreen>(LOADING_UI_PATH).untyped(),
+    ]);
+    assets.0.extend(sounds.handles());
+    create_ui(&mut commands, &asset_server, &assets);
+    spawn_failure_trigger(&mut commands, &assets);
+    commands.insert_resource(assets);
+
+    // the cake is used until the pickups file has loaded
+    commands.insert_resource(PickupKinds::fallback(pickup_handle));
 
-    let game = Game {
-        score: 0,
-        cake_eaten: 0,
+    commands.insert_resource(Game::new(
         player_handle,
-        pickup_handle,
+        pickups_handle,
         tile_handle,
         world_handle,
+    ));
+}
+
+/// show the loading screen, which moves on to the title screen once every asset is loaded,
+/// or lists what failed if any asset can't be loaded
+fn create_ui(commands: &mut Commands, asset_server: &AssetServer, assets: &LoadingAssets) {
+    let ui = spawn_screen(commands, GameState::Loading);
+    let loading_commands = UiCommands::builder().with(UiCommand::SwitchState(GameState::MainMenu));
+    commands.entity(ui).insert((
+        UiScreenRoot::new(asset_server.load(LOADING_UI_PATH)),
+        Trigger::once(TriggerCondition::AssetsLoaded(
+            assets.0.iter().cloned().collect(),
+        )),
+        LoadingTrigger,
+        loading_commands,
+    ));
+}
+
+/// show what failed once any of the assets fails to load
+fn spawn_failure_trigger(commands: &mut Commands, assets: &LoadingAssets) {
+    let failed_commands = UiCommands::builder().with(UiCommand::Custom(
+        SHOW_LOAD_FAILURE.to_string(),
+        String::new(),
+    ));
+    commands.spawn((
+        Scope::State(GameState::Loading),
+        Trigger::once(TriggerCondition::AssetsFailed(
+            assets.0.iter().cloned().collect(),
+        )),
+        LoadingTrigger,
+        failed_commands,
+    ));
+}
+
+/// wait for the models of the pickup kinds as well, as soon as the pickups file has loaded,
+/// before the loading screen can see that everything else is done
+fn wait_for_catalog_models(
+    mut added: Local<bool>,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    catalogs: Res<Assets<PickupCatalog>>,
+    mut assets: ResMut<LoadingAssets>,
+    mut triggers: Query<&mut Trigger, With<LoadingTrigger>>,
+) {
+    if *added {
+        return;
+    }
+    let Some(catalog) = catalogs.get(&game.pickups_handle) else {
+        return;
     };
-    commands.insert_resource(game);
+    *added = true;
+    // the same handles the pickup kinds load them with, as they share their paths
+    let models = catalog
+        .kinds
+        .iter()
+        .map(|kind| asset_server.load::<Scene>(kind.model.clone()).untyped())
+        .collect::<Vec<_>>();
+    for mut trigger in triggers.iter_mut() {
+        trigger.add_assets(models.iter().cloned());
+    }
+    assets.0.extend(models);
 }
 
-fn create_ui(commands: &mut Commands, scenes: impl IntoIterator<Item = Handle<Scene>>) {
-    let mut ready_ui_commands = commands.spawn(NodeBundle {
-        style: Style {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            ..default()
-        },
-        visibility: Visibility::Hidden,
-        ..default()
-    });
+/// fill the progress bars of the loading screen with the share of assets loaded
+fn update_loading_progress(
+    asset_server: Res<AssetServer>,
+    assets: Res<LoadingAssets>,
+    mut bars: Query<&mut ProgressBar>,
+) {
+    let (loaded, total) = assets.progress(&asset_server);
+    let fraction = loaded as f32 / total.max(1) as f32;
+    for mut bar in bars.iter_mut() {
+        bar.set_if_neq(ProgressBar(fraction));
+    }
+}
 
-    let ready_ui = ready_ui_commands.id();
-    let button_commands = UiCommands::builder()
-        .with(UiCommand::SwitchState(GameState::Playing))
-        .with(UiCommand::CaptureMouse)
-        .with(UiCommand::Despawn(ready_ui));
-
-    ready_ui_commands.with_children(|parent| {
-        parent
-            .spawn(ButtonBundle {
-                style: Style {
-                    width: Val::Px(150.0),
-                    height: Val::Px(65.0),
-                    border: UiRect::all(Val::Px(5.0)),
-                    // horizontally center child text
-                    justify_content: JustifyContent::Center,
-                    // vertically center child text
-                    align_items: AlignItems::Center,
-                    ..default()
-                },
-                border_color: BorderColor(Color::BLACK),
-                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                ..default()
-            })
-            .insert(button_commands)
-            .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Play!",
-                    TextStyle {
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                        ..default()
-                    },
-                ));
-            });
-    });
+/// the paths of the assets that failed to load
+fn failed_paths(world: &World) -> Vec<String> {
+    world
+        .resource::<LoadingAssets>()
+        .failed(world.resource::<AssetServer>())
+        .map(|handle| {
+            handle
+                .path()
+                .map_or_else(|| format!("{:?}", handle.id()), |path| path.to_string())
+        })
+        .collect()
+}
 
-    let mut loading_ui_commands = commands.spawn(NodeBundle {
-        style: Style {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            ..default()
-        },
-        ..default()
-    });
-    loading_ui_commands.with_children(|parent| {
+/// replace the loading screen with a list of the assets that failed, and a way to try them again
+fn show_load_failure(world: &mut World) {
+    let failed = failed_paths(world);
+    despawn_scope_now(world, Scope::State(GameState::Loading));
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    let ui = spawn_screen(&mut commands, GameState::Loading);
+    let retry_commands =
+        UiCommands::builder().with(UiCommand::Custom(RETRY_LOADING.to_string(), String::new()));
+    let quit_commands = UiCommands::builder().with(UiCommand::Terminate);
+    commands.entity(ui).with_children(|parent| {
+        spawn_heading(parent, "Error");
         parent.spawn(TextBundle::from_section(
-            "Loading...",
+            "These files could not be loaded:",
             TextStyle {
-                font_size: 64.0,
+                font_size: 30.0,
                 color: Color::rgb(0.9, 0.9, 0.9),
                 ..default()
             },
         ));
+        for path in failed {
+            parent.spawn(TextBundle::from_section(
+                path,
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::rgb(1.0, 0.8, 0.3),
+                    ..default()
+                },
+            ));
+        }
+        spawn_button(parent, "Retry", retry_commands, ());
+        spawn_button(parent, "Quit", (quit_commands, BackButton), ());
     });
+    queue.apply(world);
+}
 
-    let loading_ui = loading_ui_commands.id();
-
-    let loading_commands = UiCommands::builder().with(UiCommand::SwapUi(loading_ui, ready_ui));
+/// load the failed assets again, and go back to the loading screen to wait for them
+///
+/// failures are only watched for again once the retried assets have started loading
+fn retry_loading(world: &mut World) {
+    let failed = world
+        .resource::<LoadingAssets>()
+        .failed(world.resource::<AssetServer>())
+        .cloned()
+        .collect::<Vec<_>>();
+    despawn_scope_now(world, Scope::State(GameState::Loading));
+    let asset_server = world.resource::<AssetServer>().clone();
+    for path in failed.iter().filter_map(|handle| handle.path()) {
+        asset_server.reload(path.clone());
+    }
+    world.insert_resource(RetriedAssets {
+        ids: failed.iter().map(|handle| handle.id()).collect(),
+        grace: Timer::from_seconds(RETRY_GRACE, TimerMode::Once),
+    });
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    create_ui(
+        &mut commands,
+        &asset_server,
+        world.resource::<LoadingAssets>(),
+    );
+    queue.apply(world);
+}
 
-    loading_ui_commands.insert((
-        Trigger::ScenesLoaded(scenes.into_iter().collect()),
-        loading_commands,
-    ));
+/// watch for failures again once every retried asset has left its old failure behind,
+/// or has had time to, since a file that is still missing can fail again before it is
+/// ever seen loading
+fn watch_retried_assets(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    assets: Res<LoadingAssets>,
+    retried: Option<ResMut<RetriedAssets>>,
+) {
+    let Some(mut retried) = retried else {
+        return;
+    };
+    retried
+        .ids
+        .retain(|id| asset_server.get_load_state(*id) == Some(LoadState::Failed));
+    if !retried.ids.is_empty() && !retried.grace.tick(time.delta()).finished() {
+        return;
+    }
+    commands.remove_resource::<RetriedAssets>();
+    spawn_failure_trigger(&mut commands, &assets);
 }
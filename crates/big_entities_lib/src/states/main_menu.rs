@@ -0,0 +1,318 @@
+use super::{
+    despawn_scope,
+    settings::{create_settings, update_setting_labels},
+    GameState, Scope,
+};
+use crate::{
+    actions::{Action, Actions, BACKSPACE},
+    chunks::WorldConfiguration,
+    rng::SeedOverride,
+    ui::{
+        spawn_button, spawn_heading, spawn_screen, BackButton, Editing, UiCommand, UiCommands,
+        UiFocus,
+    },
+    Game,
+};
+use bevy::{asset::LoadedFolder, prelude::*, window::ReceivedCharacter};
+use std::any::TypeId;
+
+const WORLDS_FOLDER: &str = "worlds";
+const CREDITS: &str = include_str!("../../../../credits/CREDITS.md");
+const BEVY_URL: &str = "https://bevyengine.org";
+const KENNEY_URL: &str = "https://kenney.nl/assets/food-kit";
+/// u64::MAX has this many digits
+const MAX_SEED_DIGITS: usize = 20;
+
+/// The title screen, and the credits and settings screens it leads to
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedEntry>()
+            .add_systems(Startup, load_worlds)
+            .add_systems(Update, collect_worlds)
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                // a game abandoned from the pause menu ends here
+                (despawn_scope(Scope::Game), create_main_menu),
+            )
+            .add_systems(
+                Update,
+                (enter_seed, update_main_menu_labels)
+                    .chain()
+                    .run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(OnExit(GameState::MainMenu), finish_seed_entry)
+            .add_systems(OnEnter(GameState::Credits), create_credits)
+            .add_systems(OnEnter(GameState::Settings), create_settings)
+            .add_systems(
+                Update,
+                update_setting_labels.run_if(in_state(GameState::Settings)),
+            );
+    }
+}
+
+/// The worlds found in the worlds folder, which new games can be played in
+#[derive(Resource)]
+pub struct WorldChoices {
+    folder: Handle<LoadedFolder>,
+    worlds: Vec<Handle<WorldConfiguration>>,
+}
+
+/// The seed being typed for new games
+#[derive(Resource, Default)]
+pub struct SeedEntry {
+    text: String,
+    editing: bool,
+}
+
+/// Marks the label of the world choice button
+#[derive(Component)]
+pub struct WorldLabel;
+
+/// Marks the seed entry button
+#[derive(Component)]
+pub struct SeedButton;
+
+/// Marks the label of the seed entry button
+#[derive(Component)]
+pub struct SeedLabel;
+
+/// load every world in the worlds folder, which `collect_worlds` lists once it has loaded
+#[cfg(not(target_arch = "wasm32"))]
+fn load_worlds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WorldChoices {
+        folder: asset_server.load_folder(WORLDS_FOLDER),
+        worlds: Vec::new(),
+    });
+}
+
+/// folders can't be listed on the web, so there the worlds are loaded from a fixed list
+#[cfg(target_arch = "wasm32")]
+fn load_worlds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    const WEB_WORLDS: [&str; 2] = ["worlds/hill.world.ron", "worlds/main.world.ron"];
+    commands.insert_resource(WorldChoices {
+        folder: Handle::default(),
+        worlds: WEB_WORLDS
+            .iter()
+            .map(|path| asset_server.load(*path))
+            .collect(),
+    });
+}
+
+/// keep the list of worlds up to date with the worlds folder
+fn collect_worlds(
+    mut events: EventReader<AssetEvent<LoadedFolder>>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut choices: ResMut<WorldChoices>,
+) {
+    for event in events.read() {
+        if !(event.is_loaded_with_dependencies(&choices.folder)
+            || event.is_modified(&choices.folder))
+        {
+            continue;
+        }
+        let Some(folder) = folders.get(&choices.folder) else {
+            continue;
+        };
+        choices.worlds = folder
+            .handles
+            .iter()
+            .filter(|handle| handle.type_id() == TypeId::of::<WorldConfiguration>())
+            .map(|handle| handle.clone().typed::<WorldConfiguration>())
+            .collect();
+        choices
+            .worlds
+            .sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+    }
+}
+
+/// a short name for a world, from its file name
+fn world_name(handle: &Handle<WorldConfiguration>) -> String {
+    handle
+        .path()
+        .and_then(|path| path.path().file_name())
+        .and_then(|name| name.to_str())
+        .map(|name| name.trim_end_matches(".world.ron").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// play new games in the next world of the worlds folder
+pub fn cycle_world(world: &mut World) {
+    let Some(worlds) = world
+        .get_resource::<WorldChoices>()
+        .map(|choices| choices.worlds.clone())
+    else {
+        return;
+    };
+    if worlds.is_empty() {
+        warn!("no other worlds were found in {WORLDS_FOLDER}");
+        return;
+    }
+    let mut game = world.resource_mut::<Game>();
+    let next = worlds
+        .iter()
+        .position(|handle| *handle == game.world_handle)
+        .map_or(0, |i| (i + 1) % worlds.len());
+    game.world_handle = worlds[next].clone();
+}
+
+/// start typing a seed, or finish typing it
+pub fn toggle_seed_entry(world: &mut World) {
+    let editing = {
+        let mut entry = world.resource_mut::<SeedEntry>();
+        entry.editing = !entry.editing;
+        entry.editing
+    };
+    let buttons = world
+        .query_filtered::<Entity, With<SeedButton>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    for button in buttons {
+        if editing {
+            world.entity_mut(button).insert(Editing);
+            world.resource_mut::<UiFocus>().0 = Some(button);
+        } else {
+            world.entity_mut(button).remove::<Editing>();
+        }
+    }
+}
+
+/// stop typing the seed when leaving the title screen
+fn finish_seed_entry(mut entry: ResMut<SeedEntry>) {
+    entry.editing = false;
+}
+
+/// type the seed for new games, with the keyboard or by changing digits on a gamepad
+fn enter_seed(
+    actions: Actions,
+    mut typed: EventReader<ReceivedCharacter>,
+    mut entry: ResMut<SeedEntry>,
+    mut seed: ResMut<SeedOverride>,
+) {
+    let typed = typed.read().map(|event| event.char).collect::<Vec<_>>();
+    if !entry.editing {
+        return;
+    }
+    let text = &mut entry.text;
+    let before = text.clone();
+    for c in typed {
+        if c == BACKSPACE {
+            text.pop();
+        } else if c.is_ascii_digit() && text.len() < MAX_SEED_DIGITS {
+            text.push(c);
+        }
+    }
+    if actions.just_pressed(Action::DeleteLetter) {
+        text.pop();
+    }
+    if actions.just_pressed(Action::AddLetter) && text.len() < MAX_SEED_DIGITS {
+        text.push('0');
+    }
+    let step = match (
+        actions.just_pressed(Action::NextLetter),
+        actions.just_pressed(Action::PreviousLetter),
+    ) {
+        (true, false) => 1,
+        (false, true) => 9,
+        _ => 0,
+    };
+    if step != 0 {
+        let digit = text.pop().and_then(|c| c.to_digit(10)).unwrap_or(0);
+        text.extend(char::from_digit((digit + step) % 10, 10));
+    }
+    if *text != before {
+        // seeds too big to fit are ignored, like an empty seed
+        seed.0 = text.parse().ok();
+    }
+}
+
+/// show the chosen world and seed on their buttons
+fn update_main_menu_labels(
+    game: Res<Game>,
+    entry: Res<SeedEntry>,
+    seed: Res<SeedOverride>,
+    mut worlds: Query<&mut Text, (With<WorldLabel>, Without<SeedLabel>)>,
+    mut seeds: Query<&mut Text, (With<SeedLabel>, Without<WorldLabel>)>,
+) {
+    for mut text in worlds.iter_mut() {
+        text.sections[0].value = format!("World: {}", world_name(&game.world_handle));
+    }
+    let shown = match (entry.editing, seed.0) {
+        (true, _) => format!("{}_", entry.text),
+        (false, Some(seed)) => seed.to_string(),
+        (false, None) => "default".to_string(),
+    };
+    for mut text in seeds.iter_mut() {
+        text.sections[0].value = format!("Seed: {shown}");
+    }
+}
+
+pub fn create_main_menu(
+    mut commands: Commands,
+    mut entry: ResMut<SeedEntry>,
+    seed: Res<SeedOverride>,
+) {
+    entry.text = seed.0.map(|seed| seed.to_string()).unwrap_or_default();
+    let ui = spawn_screen(&mut commands, GameState::MainMenu);
+
+    let new_game_commands = UiCommands::builder()
+        .with(UiCommand::SwitchState(GameState::Playing))
+        .with(UiCommand::CaptureMouse);
+    let continue_commands = UiCommands::builder().with(UiCommand::LoadGame(ui));
+    let replay_commands = UiCommands::builder().with(UiCommand::WatchReplay);
+    let world_commands = UiCommands::builder().with(UiCommand::CycleWorld);
+    let seed_commands = UiCommands::builder().with(UiCommand::EditSeed);
+    let settings_commands = UiCommands::builder().with(UiCommand::SwitchState(GameState::Settings));
+    let credits_commands = UiCommands::builder().with(UiCommand::SwitchState(GameState::Credits));
+    let quit_commands = UiCommands::builder().with(UiCommand::Terminate);
+
+    commands.entity(ui).with_children(|parent| {
+        spawn_heading(parent, "Big Entities");
+        spawn_button(parent, "New Game", new_game_commands, ());
+        spawn_button(parent, "Continue", continue_commands, ());
+        spawn_button(parent, "Watch Replay", replay_commands, ());
+        spawn_button(parent, "World:", world_commands, WorldLabel);
+        spawn_button(parent, "Seed:", (seed_commands, SeedButton), SeedLabel);
+        spawn_button(parent, "Settings", settings_commands, ());
+        spawn_button(parent, "Credits", credits_commands, ());
+        spawn_button(parent, "Quit", quit_commands, ());
+    });
+}
+
+/// the commands of a button that returns to the title screen
+pub fn back_to_main_menu() -> (UiCommands, BackButton) {
+    (
+        UiCommands::builder().with(UiCommand::SwitchState(GameState::MainMenu)),
+        BackButton,
+    )
+}
+
+/// show the credits file
+fn create_credits(mut commands: Commands) {
+    let ui = spawn_screen(&mut commands, GameState::Credits);
+    commands.entity(ui).with_children(|parent| {
+        spawn_heading(parent, "Credits");
+        parent.spawn(TextBundle::from_section(
+            CREDITS,
+            TextStyle {
+                font_size: 24.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        ));
+        spawn_button(
+            parent,
+            "Bevy Engine",
+            UiCommands::builder().with(UiCommand::OpenUrl(BEVY_URL.to_string())),
+            (),
+        );
+        spawn_button(
+            parent,
+            "Kenney Assets",
+            UiCommands::builder().with(UiCommand::OpenUrl(KENNEY_URL.to_string())),
+            (),
+        );
+        spawn_button(parent, "Back", back_to_main_menu(), ());
+    });
+}
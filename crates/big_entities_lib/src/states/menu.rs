@@ -1,88 +1,27 @@
 use bevy::prelude::*;
 
-use crate::ui::{UiCommand, UiCommands};
+use crate::ui::{screen::UiScreenRoot, spawn_screen};
 
 use super::GameState;
 
-pub fn create_menu_ui(mut commands: Commands) {
-    let mut ui_commands = commands.spawn(NodeBundle {
-        style: Style {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            ..default()
-        },
-        // visibility: Visibility::Hidden,
-        ..default()
-    });
+const MENU_PATH: &str = "ui/pause.ui.ron";
 
-    let ui_id = ui_commands.id();
-    let continue_button_commands = UiCommands::builder()
-        .with(UiCommand::SwitchState(GameState::Playing))
-        .with(UiCommand::CaptureMouse)
-        .with(UiCommand::Despawn(ui_id));
+/// show the pause menu, as described in its screen file
+pub fn create_menu_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let ui = spawn_screen(&mut commands, GameState::Menu);
+    commands
+        .entity(ui)
+        .insert(UiScreenRoot::new(asset_server.load(MENU_PATH)));
+}
 
-    let exit_button_commands = UiCommands::builder()
-        .with(UiCommand::SwitchState(GameState::GameOver))
-        .with(UiCommand::ReleaseMouse)
-        .with(UiCommand::Despawn(ui_id))
-        .with(UiCommand::Terminate);
+/// stop the game clock, so nothing that runs on game time moves while the menu is open
+pub fn pause_game(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
 
-    ui_commands.with_children(|parent| {
-        parent
-            .spawn(ButtonBundle {
-                style: Style {
-                    width: Val::Px(150.0),
-                    height: Val::Px(65.0),
-                    border: UiRect::all(Val::Px(5.0)),
-                    // horizontally center child text
-                    justify_content: JustifyContent::Center,
-                    // vertically center child text
-                    align_items: AlignItems::Center,
-                    ..default()
-                },
-                border_color: BorderColor(Color::BLACK),
-                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                ..default()
-            })
-            .insert(continue_button_commands)
-            .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Continue",
-                    TextStyle {
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                        ..default()
-                    },
-                ));
-            });
-        parent
-            .spawn(ButtonBundle {
-                style: Style {
-                    width: Val::Px(150.0),
-                    height: Val::Px(65.0),
-                    border: UiRect::all(Val::Px(5.0)),
-                    // horizontally center child text
-                    justify_content: JustifyContent::Center,
-                    // vertically center child text
-                    align_items: AlignItems::Center,
-                    ..default()
-                },
-                border_color: BorderColor(Color::BLACK),
-                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                ..default()
-            })
-            .insert(exit_button_commands)
-            .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Exit",
-                    TextStyle {
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                        ..default()
-                    },
-                ));
-            });
-    });
+/// restart the game clock where it stopped
+///
+/// mouse movement made in the menu is dropped as play starts again, see `ActionPlugin`
+pub fn resume_game(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
 }
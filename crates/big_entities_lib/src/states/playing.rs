@@ -1,172 +1,170 @@
-use super::GameState;
+use super::{GameState, Scope};
 use crate::{
-    chunks::{WorldConfiguration, ChunkData, BoardPosition, Cell, Tile},
-    pawn::{free_movement::FirstPersonPawn, Player},
-    pickup::{spawn_a_pickup, Pickup},
-    ui::score::DataDisplay,
+    actions::{Action, Actions},
+    chunks::{BoardPosition, ChunkMap, WorldConfiguration},
+    effects::StatusEffects,
+    pawn::{
+        control_mode::{apply_control_mode, ControlMode},
+        free_movement::FirstPersonPawn,
+        gravity::{FellOffBoard, VerticalMotion},
+        Player, PlayerView, Teleported,
+    },
+    pickup::{choose_pickup_tile, spawn_a_pickup, AutoRespawn, PickupKinds},
+    replay::ReplayPlayback,
+    rng::{choose_seed, GameRng, SeedOverride},
+    save::{PendingLoad, SavedEffect},
     Game,
 };
-use bevy::{
-    prelude::*,
-    window::{CursorGrabMode, PrimaryWindow},
-};
-use rand::Rng;
-use std::f32::consts::PI;
+use bevy::prelude::*;
+use std::{f32::consts::PI, time::Duration};
 
-/// reset the game to a valid initial state
+/// reset the game to a valid initial state, or to the saved game waiting to be loaded
 pub fn setup(
     mut commands: Commands,
     mut game: ResMut<Game>,
-    cameras: Query<Entity, With<Camera>>,
+    views: Query<Entity, With<PlayerView>>,
     boards: Res<Assets<WorldConfiguration>>,
+    seed_override: Res<SeedOverride>,
+    mut rng: ResMut<GameRng>,
+    mut chunks: ResMut<ChunkMap>,
+    pickup_kinds: Res<PickupKinds>,
+    mut pending: ResMut<PendingLoad>,
+    playback: Option<Res<ReplayPlayback>>,
 ) {
-    game.cake_eaten = 0;
-    game.score = 0;
+    let save = pending.0.take();
+    game.cake_eaten = save.as_ref().map_or(0, |save| save.cake_eaten);
+    game.score = save.as_ref().map_or(0, |save| save.score);
+    game.play_time = save.as_ref().map_or(0.0, |save| save.play_time);
     let Some(board_config) = boards.get(game.world_handle.clone()) else {
         warn!("expected board config asset to be loaded by now");
         return;
     };
+    let seed = match (&save, playback) {
+        (Some(save), _) => save.seed,
+        (None, Some(playback)) => playback.replay.seed,
+        (None, None) => choose_seed(&seed_override, board_config.seed),
+    };
+    *rng = GameRng::new(seed);
+    if let Some(save) = &save {
+        rng.set_position(save.rng_position);
+    }
+    info!("starting game with seed {}", rng.seed());
 
-    let board = commands.spawn(SpatialBundle::default()).id();
-    let board_pieces: Vec<_> = (0..board_config.height)
-        .flat_map(|y| {
-            (0..board_config.width).map(move |x| (x, y, rand::thread_rng().gen_range(-0.1..0.1)))
-        })
-        .map(|(x, y, height)| {
-            let entity = commands
-                .spawn((
-                    SceneBundle {
-                        transform: Transform::from_xyz(x as f32, height - 0.2, y as f32),
-                        scene: game.tile_handle.clone(),
-                        ..default()
-                    },
-                    Cell { height },
-                    BoardPosition { x, y },
-                ))
-                .id();
-            commands.entity(board).add_child(entity);
-            (x, y, entity.clone(), height)
-        })
-        .collect();
-
-    let board_cache: ChunkData = ChunkData::new(
-        board_config.width,
-        board_config.height,
-        board_pieces
-            .into_iter()
-            .map(|(_x, _y, entity, height)| Tile { entity, height })
-            .collect(),
-    );
-
-    let Ok(camera) = cameras.get_single() else {
-        warn!("unexpected camera count");
+    let Ok(camera) = views.get_single() else {
+        warn!("unexpected player view count");
         return;
     };
 
-    let player_position = BoardPosition {
-        x: board_config.width / 2,
-        y: board_config.height / 2,
+    let player_position = match &save {
+        Some(save) => save.player.position,
+        None => BoardPosition {
+            x: board_config.width / 2,
+            y: board_config.height / 2,
+        },
     };
 
-    let translation = Vec3::new(
-        player_position.x as f32,
-        board_cache.get_height(player_position.x, player_position.y),
-        player_position.y as f32,
-    );
-
-    commands.entity(board).insert(board_cache);
-
-    let rotation = Quat::from_rotation_y(-PI / 2.);
-
-    commands
-        .spawn(SceneBundle {
-            transform: Transform {
-                translation,
-                rotation,
-                ..default()
-            },
-            scene: game.player_handle.clone(),
-            ..default()
-        })
-        .insert((
-            player_position,
-            Player,
-            // Facing::Up,
-            // InputRateLimit(Timer::new(Duration::from_millis(200), TimerMode::Once)),
-            // FollowMe::new(camera),
-            FirstPersonPawn::new(camera),
-        ));
-
-    commands.spawn((
-        DataDisplay::Score,
-        TextBundle::from_section(
-            "Score:",
-            TextStyle {
-                font_size: 40.0,
-                color: Color::rgb(0.5, 0.5, 1.0),
-                ..default()
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            left: Val::Px(5.0),
-            ..default()
-        }),
-    ));
+    // the world is loaded in chunks around the player
+    chunks.clear();
+    for chunk in save.iter().flat_map(|save| save.chunks.iter()) {
+        chunks.restore_heights(chunk.coord, chunk.heights.clone());
+    }
+    chunks.load_around(&mut commands, board_config, &rng, player_position);
 
-    commands.spawn((
-        DataDisplay::PickupPosition,
-        TextBundle::from_section(
-            "Cake:",
-            TextStyle {
-                font_size: 40.0,
-                color: Color::rgb(0.5, 0.5, 1.0),
-                ..default()
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(50.0),
-            left: Val::Px(5.0),
+    let mut transform = match &save {
+        Some(save) => Transform {
+            translation: save.player.translation,
+            rotation: save.player.rotation,
             ..default()
-        }),
-    ));
-
-    commands.spawn((
-        DataDisplay::PlayerPosition,
-        TextBundle::from_section(
-            "Cake:",
-            TextStyle {
-                font_size: 40.0,
-                color: Color::rgb(0.5, 0.5, 1.0),
-                ..default()
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(90.0),
-            left: Val::Px(5.0),
+        },
+        None => Transform {
+            translation: Vec3::new(
+                player_position.x as f32,
+                board_config.generate_height(&rng, player_position.x, player_position.y),
+                player_position.y as f32,
+            ),
+            rotation: Quat::from_rotation_y(-PI / 2.),
             ..default()
-        }),
-    ));
-
-    {
-        let x = rand::thread_rng().gen_range(0..board_config.width);
-        let y = rand::thread_rng().gen_range(0..board_config.height);
+        },
+    };
+    let translation = transform.translation;
+    let control_mode = save
+        .as_ref()
+        .map_or(board_config.control_mode, |save| save.player.control_mode);
+
+    let (motion, effects) = match &save {
+        Some(save) => (
+            save.player.motion.clone(),
+            save.player
+                .effects
+                .iter()
+                .map(SavedEffect::restore)
+                .collect(),
+        ),
+        None => (VerticalMotion::new(translation), StatusEffects::default()),
+    };
+    let mut player = commands.spawn((player_position, Player, motion, effects, Scope::Game));
+    apply_control_mode(
+        &mut player,
+        control_mode,
+        camera,
+        &mut transform,
+        player_position,
+    );
+    if let Some(look_rotation) = save.as_ref().and_then(|save| save.player.look_rotation) {
+        if control_mode == ControlMode::FirstPerson {
+            let mut pawn = FirstPersonPawn::new(camera);
+            pawn.look_rotation = look_rotation;
+            player.insert(pawn);
+        }
+    }
+    player.insert(TransformBundle::from_transform(transform));
+
+    let saved_pickups = save.map(|save| save.pickups).unwrap_or_default();
+    for saved in saved_pickups.iter() {
+        let Some((index, kind)) = pickup_kinds.find(&saved.kind) else {
+            warn!("saved game has unknown pickup kind {}", saved.kind);
+            continue;
+        };
+        let mut timer = Timer::from_seconds(kind.lifetime, TimerMode::Once);
+        timer.set_elapsed(Duration::from_secs_f32(
+            (kind.lifetime - saved.remaining).max(0.0),
+        ));
+        let (x, y, height) = (saved.position.x, saved.position.y, saved.translation.y);
+        if let Some(pickup) = spawn_a_pickup(&mut commands, &pickup_kinds, index, x, y, height) {
+            commands.entity(pickup).insert((
+                AutoRespawn(timer),
+                Transform::from_translation(saved.translation),
+            ));
+        }
+    }
 
-        spawn_a_pickup(&mut commands, game.pickup_handle.clone(), x, y, 0.0);
+    // a new game starts with one pickup, and so does a save that had none left
+    if saved_pickups.is_empty() {
+        let bounds = chunks.tile_bounds(board_config);
+        // the chunks aren't spawned yet, so the world says which tiles can be entered
+        let tile = choose_pickup_tile(&mut *rng, bounds, |x, y| {
+            board_config.passable(x, y) && BoardPosition { x, y } != player_position
+        });
+        let Some(BoardPosition { x, y }) = tile else {
+            warn!("found no free tile for the first pickup");
+            return;
+        };
+        let height = board_config.generate_height(&rng, x, y) + 0.2;
+
+        if let Some(kind) = pickup_kinds.choose(&mut *rng) {
+            spawn_a_pickup(&mut commands, &pickup_kinds, kind, x, y, height);
+        }
     }
 }
 
-/// remove all entities from the scene except cameras and windows
-pub fn teardown(
-    mut commands: Commands,
-    entities: Query<Entity, (Without<Camera>, Without<Window>)>,
-) {
-    for entity in &entities {
-        commands.entity(entity).despawn();
-    }
+/// a new game needs setting up, rather than resuming the paused one
+pub fn no_game_in_progress(players: Query<(), With<Player>>) -> bool {
+    players.is_empty()
+}
+
+/// no switch to another state has been asked for yet
+pub fn no_state_change_pending(next_state: Res<NextState<GameState>>) -> bool {
+    next_state.0.is_none()
 }
 
 /// end the game if score is below a threshold
@@ -176,43 +174,36 @@ pub fn end_game(game: Res<Game>, mut next_state: ResMut<NextState<GameState>>) {
     }
 }
 
-/// update the text of the scoreboard
-pub fn update_scoreboard(
-    game: Res<Game>,
-    mut query: Query<(&mut Text, &DataDisplay)>,
-    pickups: Query<&BoardPosition, (With<Pickup>, Without<DataDisplay>, Without<Player>)>,
-    players: Query<(&BoardPosition, &FirstPersonPawn), (With<Player>, Without<DataDisplay>)>,
+/// keep track of how long the current game has been played
+pub fn count_play_time(time: Res<Time>, mut game: ResMut<Game>) {
+    game.play_time += time.delta_seconds();
+}
+
+/// put players who fell off the board back where they last stood
+pub fn respawn_fallen(
+    mut fell: EventReader<FellOffBoard>,
+    mut players: Query<(&mut Transform, &mut VerticalMotion), With<Player>>,
+    views: Query<Entity, With<PlayerView>>,
+    mut teleported: EventWriter<Teleported>,
 ) {
-    for (mut text, data) in query.iter_mut() {
-        use DataDisplay as DD;
-        match data {
-            DD::Score => text.sections[0].value = format!("Score: {}", game.score),
-            DD::PickupPosition => {
-                let Some(bp) = pickups.iter().next() else {
-                    continue;
-                };
-                text.sections[0].value = format!("Cake: {},{}", bp.x, bp.y);
-            }
-            DD::PlayerPosition => {
-                let Some((bp, fpp)) = players.iter().next() else {
-                    continue;
-                };
-                text.sections[0].value =
-                    format!("Player: {},{}, look {}", bp.x, bp.y, fpp.look_rotation);
-            }
-        }
+    for FellOffBoard { entity } in fell.read() {
+        let Ok((mut transform, mut motion)) = players.get_mut(*entity) else {
+            continue;
+        };
+        transform.translation = motion.last_ground;
+        motion.velocity = 0.0;
+        motion.grounded = true;
+        teleported.send_batch(
+            views
+                .iter()
+                .chain([*entity])
+                .map(|entity| Teleported { entity }),
+        );
     }
 }
 
-pub fn enter_menu(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut next_state: ResMut<NextState<GameState>>,
-    mut windows: Query<&mut Window, With<PrimaryWindow>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+pub fn enter_menu(actions: Actions, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
         next_state.set(GameState::Menu);
-        let mut window = windows.get_single_mut().unwrap();
-        window.cursor.visible = true;
-        window.cursor.grab_mode = CursorGrabMode::None;
     }
 }
@@ -0,0 +1,90 @@
+use super::{main_menu::back_to_main_menu, GameState};
+use crate::{
+    settings::{Setting, Settings},
+    ui::{spawn_button, spawn_heading, spawn_screen, UiCommand, UiCommands},
+};
+use bevy::prelude::*;
+
+/// settings changed in steps, shown with buttons to step them down and up
+const STEPPED: [Setting; 5] = [
+    Setting::MouseSensitivity,
+    Setting::ZoomMin,
+    Setting::ZoomMax,
+    Setting::MusicVolume,
+    Setting::SfxVolume,
+];
+/// settings that are on or off, shown as a button that switches them
+const SWITCHED: [Setting; 3] = [Setting::Msaa, Setting::Shadows, Setting::ShowFps];
+
+/// Marks a label showing the value of a setting
+#[derive(Component)]
+pub struct SettingLabel(pub Setting);
+
+/// a row of ui, centered in its parent
+fn spawn_row(parent: &mut ChildBuilder, children: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(children);
+}
+
+pub fn create_settings(mut commands: Commands, settings: Res<Settings>) {
+    let ui = spawn_screen(&mut commands, GameState::Settings);
+    commands.entity(ui).with_children(|parent| {
+        spawn_heading(parent, "Settings");
+        for setting in STEPPED {
+            spawn_row(parent, |row| {
+                let down = UiCommands::builder().with(UiCommand::ChangeSetting(setting, -1));
+                let up = UiCommands::builder().with(UiCommand::ChangeSetting(setting, 1));
+                spawn_button(row, "-", down, ());
+                row.spawn((
+                    SettingLabel(setting),
+                    TextBundle::from_section(
+                        settings.describe(setting),
+                        TextStyle {
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        width: Val::Px(450.0),
+                        ..default()
+                    }),
+                ));
+                spawn_button(row, "+", up, ());
+            });
+        }
+        spawn_row(parent, |row| {
+            for setting in SWITCHED {
+                let switch = UiCommands::builder().with(UiCommand::ChangeSetting(setting, 1));
+                spawn_button(
+                    row,
+                    &settings.describe(setting),
+                    switch,
+                    SettingLabel(setting),
+                );
+            }
+        });
+        spawn_button(parent, "Back", back_to_main_menu(), ());
+    });
+}
+
+/// keep setting labels showing the current values
+pub fn update_setting_labels(
+    settings: Res<Settings>,
+    mut labels: Query<(&mut Text, &SettingLabel)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, SettingLabel(setting)) in labels.iter_mut() {
+        text.sections[0].value = settings.describe(*setting);
+    }
+}
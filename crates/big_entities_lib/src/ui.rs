@@ -1,17 +1,32 @@
-use std::process;
+use std::{collections::HashMap, process, sync::Arc};
 
 use bevy::{
     app::AppExit,
     asset::{LoadState, UntypedHandle},
-    ecs::system::Command,
+    ecs::system::{Command, CommandQueue},
     prelude::*,
     window::{CursorGrabMode, PrimaryWindow},
 };
 use smallvec::SmallVec;
 
-use crate::states::GameState;
+use crate::{
+    actions::{Action, Actions},
+    audio::{play_sound, PlaySfx, Sfx},
+    replay::watch_replay,
+    save::{load_save, save_snapshot},
+    settings::{change_setting, Setting},
+    states::{
+        despawn_scope_now,
+        main_menu::{cycle_world, toggle_seed_entry},
+        GameState, Scope,
+    },
+    Game,
+};
 pub mod score;
 pub mod fps;
+pub mod screen;
+
+use screen::open_screen;
 
 #[derive(Component)]
 pub struct UiCommands(SmallVec<[UiCommand; 2]>);
@@ -46,19 +61,134 @@ pub enum UiCommand {
     CaptureMouse,
     ReleaseMouse,
     Terminate,
+    /// write the paused game to the save file
+    SaveGame,
+    /// start playing the saved game, despawning the given ui
+    ///
+    /// stays on the current screen if there is no usable save
+    LoadGame(Entity),
+    /// play back the replay file of the last game, or the one picked by the replay
+    /// environment variable
+    WatchReplay,
+    /// choose the next world for new games
+    CycleWorld,
+    /// start or stop typing the seed for new games
+    EditSeed,
+    /// step a setting up or down by this many steps, or switch it on or off
+    ChangeSetting(Setting, i32),
+    /// open a web page in the browser
+    OpenUrl(String),
+    /// play the sound at this asset path
+    PlaySound(String),
+    /// show the screen file at this asset path, belonging to the current state
+    SpawnScreen(String),
+    /// send an event, see `UiCommand::send_event`
+    SendEvent(Arc<dyn Fn(&mut World) + Send + Sync>),
+    /// replace the current state's ui with an error message and a quit button
+    ShowError(String),
+    /// run the handler registered under this name with `add_ui_command`, passing it the argument
+    Custom(String, String),
+}
+
+impl UiCommand {
+    /// a command that sends a copy of this event each time it runs
+    pub fn send_event<E: Event + Clone>(event: E) -> UiCommand {
+        UiCommand::SendEvent(Arc::new(move |world| {
+            world.send_event(event.clone());
+        }))
+    }
+}
+
+/// Runs a custom UiCommand, given its argument
+pub type UiCommandHandler = Arc<dyn Fn(&mut World, &str) + Send + Sync>;
+
+/// The handlers of custom UiCommands, by name
+#[derive(Resource, Default)]
+pub struct UiCommandHandlers(HashMap<String, UiCommandHandler>);
+
+/// Lets plugins add their own UiCommands without changing the enum
+pub trait AddUiCommand {
+    /// run the handler whenever a `UiCommand::Custom` with this name runs
+    fn add_ui_command(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl Fn(&mut World, &str) + Send + Sync + 'static,
+    ) -> &mut Self;
 }
 
-/// Trigger an associated InteractionCommand
+impl AddUiCommand for App {
+    fn add_ui_command(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl Fn(&mut World, &str) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(UiCommandHandlers::default)
+            .0
+            .insert(name.into(), Arc::new(handler));
+        self
+    }
+}
+
+/// Runs the UiCommands of its entity when its condition becomes met
+///
+/// it fires once and is then removed, unless it repeats, in which case it fires
+/// again each time the condition stops being met and then becomes met again
 #[derive(Component)]
-pub enum Trigger {
-    /// trigger when the given assets are loaded
+pub struct Trigger {
+    condition: TriggerCondition,
+    repeat: bool,
+    met: bool,
+}
+
+impl Trigger {
+    pub fn once(condition: TriggerCondition) -> Trigger {
+        Trigger {
+            condition,
+            repeat: false,
+            met: false,
+        }
+    }
+    pub fn repeating(condition: TriggerCondition) -> Trigger {
+        Trigger {
+            condition,
+            repeat: true,
+            met: false,
+        }
+    }
+    /// wait on these assets as well, if this trigger waits on assets loading or failing
+    pub fn add_assets(&mut self, assets: impl IntoIterator<Item = UntypedHandle>) {
+        match &mut self.condition {
+            TriggerCondition::AssetsLoaded(handles) | TriggerCondition::AssetsFailed(handles) => {
+                handles.extend(assets)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// What a Trigger waits for
+pub enum TriggerCondition {
+    /// the given assets are loaded
     ///
     /// this must use UntypedHandle to support any asset
     AssetsLoaded(SmallVec<[UntypedHandle; 2]>),
-    /// trigger when the given scene assets are loaded
+    /// the given scene assets are loaded
     ///
     /// this handle type is smaller, so more can be stored inline
     ScenesLoaded(SmallVec<[Handle<Scene>; 3]>),
+    /// any of the given assets failed to load
+    AssetsFailed(SmallVec<[UntypedHandle; 2]>),
+    /// the timer finished, ticking with game time
+    ///
+    /// a repeating timer fires a repeating trigger each time it wraps around
+    Elapsed(Timer),
+    /// the game is in the given state
+    StateEntered(GameState),
+    /// the score is at least this much
+    ScoreReached(i32),
+    /// any key or button bound to the action is held down
+    ActionPressed(Action),
 }
 
 impl Command for UiCommand {
@@ -103,6 +233,167 @@ impl Command for UiCommand {
                 };
                 exit_queue.send(AppExit);
             }
+            IC::SaveGame => save_snapshot(world),
+            IC::CycleWorld => cycle_world(world),
+            IC::EditSeed => toggle_seed_entry(world),
+            IC::ChangeSetting(setting, steps) => change_setting(world, setting, steps),
+            IC::OpenUrl(url) => {
+                if let Err(e) = webbrowser::open(&url) {
+                    warn!("could not open {url}: {e}");
+                }
+            }
+            IC::PlaySound(path) => play_sound(world, &path),
+            IC::SpawnScreen(path) => open_screen(world, path),
+            IC::SendEvent(send) => send(world),
+            IC::ShowError(message) => show_error(world, &message),
+            IC::Custom(name, argument) => {
+                let Some(handler) = world
+                    .get_resource::<UiCommandHandlers>()
+                    .and_then(|handlers| handlers.0.get(&name).cloned())
+                else {
+                    warn!("no handler was added for the ui command {name}");
+                    return;
+                };
+                handler(world, &argument);
+            }
+            IC::LoadGame(ui) => {
+                if load_save(world) {
+                    // the loaded game replaces any game that is paused
+                    despawn_scope_now(world, Scope::Game);
+                    IC::SwitchState(GameState::Playing).apply(world);
+                    IC::CaptureMouse.apply(world);
+                    IC::Despawn(ui).apply(world);
+                }
+            }
+            IC::WatchReplay => watch_replay(world),
+        }
+    }
+}
+
+/// The button selected by gamepad or keyboard navigation
+#[derive(Resource, Default)]
+pub struct UiFocus(pub Option<Entity>);
+
+/// Marks the button that is pressed by the Back action
+#[derive(Component)]
+pub struct BackButton;
+
+/// Marks a button whose text is being typed, so navigating doesn't move the focus off it
+#[derive(Component)]
+pub struct Editing;
+
+/// a full screen column of centered ui, belonging to the given state
+pub fn screen_bundle(state: GameState) -> impl Bundle {
+    (
+        Scope::State(state),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+    )
+}
+
+pub fn spawn_screen(commands: &mut Commands, state: GameState) -> Entity {
+    commands.spawn(screen_bundle(state)).id()
+}
+
+pub fn spawn_heading(parent: &mut ChildBuilder, text: &str) {
+    parent.spawn(TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size: 80.0,
+            color: Color::rgb(0.5, 0.5, 1.0),
+            ..default()
+        },
+    ));
+}
+
+/// spawn a button showing the given label
+///
+/// `button` is added to the button, such as its UiCommands, and `text` to its label
+pub fn spawn_button(parent: &mut ChildBuilder, label: &str, button: impl Bundle, text: impl Bundle) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                min_width: Val::Px(150.0),
+                height: Val::Px(65.0),
+                border: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::horizontal(Val::Px(10.0)),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            border_color: BorderColor(Color::BLACK),
+            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 40.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+                text,
+            ));
+        });
+}
+
+/// replace the ui of the current state with an error message and a quit button
+pub fn show_error(world: &mut World, message: &str) {
+    let state = *world.resource::<State<GameState>>().get();
+    despawn_scope_now(world, Scope::State(state));
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    let ui = spawn_screen(&mut commands, state);
+    let quit_commands = UiCommands::builder().with(UiCommand::Terminate);
+    commands.entity(ui).with_children(|parent| {
+        spawn_heading(parent, "Error");
+        parent.spawn(TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 30.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        ));
+        spawn_button(parent, "Quit", (quit_commands, BackButton), ());
+    });
+    queue.apply(world);
+}
+
+/// color a button to show how it's being interacted with
+fn paint_button(
+    interaction: Interaction,
+    color: &mut BackgroundColor,
+    border_color: &mut BorderColor,
+) {
+    match interaction {
+        Interaction::Pressed => {
+            *color = Color::rgb(0.20, 0.15, 0.15).into();
+            border_color.0 = Color::RED;
+        }
+        Interaction::Hovered => {
+            *color = Color::rgb(0.15, 0.20, 0.15).into();
+            border_color.0 = Color::WHITE;
+        }
+        Interaction::None => {
+            *color = Color::rgb(0.15, 0.15, 0.15).into();
+            border_color.0 = Color::BLACK;
         }
     }
 }
@@ -111,6 +402,7 @@ impl Command for UiCommand {
 pub fn button_clicked(
     mut interaction_query: Query<
         (
+            Entity,
             &Interaction,
             &mut BackgroundColor,
             &mut BorderColor,
@@ -118,57 +410,161 @@ pub fn button_clicked(
         ),
         (Changed<Interaction>, With<Button>),
     >,
+    focus: Res<UiFocus>,
     mut commands: Commands,
+    mut sfx: EventWriter<PlaySfx>,
 ) {
-    for (interaction, mut color, mut border_color, ic) in &mut interaction_query {
+    for (entity, interaction, mut color, mut border_color, ic) in &mut interaction_query {
+        // the focused button stays highlighted when the mouse leaves it
+        let shown = match *interaction {
+            Interaction::None if focus.0 == Some(entity) => Interaction::Hovered,
+            interaction => interaction,
+        };
+        paint_button(shown, &mut color, &mut border_color);
         match *interaction {
             Interaction::Pressed => {
-                *color = Color::rgb(0.20, 0.15, 0.15).into();
-                border_color.0 = Color::RED;
-
+                sfx.send(PlaySfx(Sfx::Press));
                 ic.add_all(&mut commands);
             }
-            Interaction::Hovered => {
-                *color = Color::rgb(0.15, 0.20, 0.15).into();
-                border_color.0 = Color::WHITE;
-            }
-            Interaction::None => {
-                *color = Color::rgb(0.15, 0.15, 0.15).into();
-                border_color.0 = Color::BLACK;
+            // the focused button was already highlighted, so it doesn't sound again
+            Interaction::Hovered if focus.0 != Some(entity) => sfx.send(PlaySfx(Sfx::Hover)),
+            _ => {}
+        }
+    }
+}
+
+/// move focus between visible buttons with the navigate actions,
+/// and run the focused button's commands with the Confirm action
+pub fn navigate_buttons(
+    actions: Actions,
+    mut focus: ResMut<UiFocus>,
+    mut buttons: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &InheritedVisibility,
+            &UiCommands,
+            &mut BackgroundColor,
+            &mut BorderColor,
+            Has<BackButton>,
+        ),
+        With<Button>,
+    >,
+    editing: Query<(), With<Editing>>,
+    mut commands: Commands,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let mut visible: Vec<(Entity, Vec3)> = buttons
+        .iter()
+        .filter(|(_, _, visibility, ..)| visibility.get())
+        .map(|(entity, transform, ..)| (entity, transform.translation()))
+        .collect();
+    if visible.is_empty() {
+        focus.0 = None;
+        return;
+    }
+    // reading order: ui coordinates grow downward, so top to bottom then left to right
+    visible.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    let count = visible.len();
+    let current = focus
+        .0
+        .and_then(|focused| visible.iter().position(|(entity, _)| *entity == focused));
+    // a button being typed into keeps the focus until it is confirmed
+    let typing = focus.0.is_some_and(|focused| editing.contains(focused));
+    let next = if typing {
+        current
+    } else if actions.just_pressed(Action::NavigateNext) {
+        Some(current.map(|i| (i + 1) % count).unwrap_or(0))
+    } else if actions.just_pressed(Action::NavigatePrevious) {
+        Some(current.map(|i| (i + count - 1) % count).unwrap_or(count - 1))
+    } else {
+        current
+    };
+    let next_focus = next.map(|i| visible[i].0);
+    if next_focus != focus.0 {
+        for (entity, interaction) in [(focus.0, Interaction::None), (next_focus, Interaction::Hovered)] {
+            if let Some((.., mut color, mut border_color, _)) = entity.and_then(|e| buttons.get_mut(e).ok()) {
+                paint_button(interaction, &mut color, &mut border_color);
             }
         }
+        focus.0 = next_focus;
+        if next_focus.is_some() {
+            sfx.send(PlaySfx(Sfx::Hover));
+        }
+    }
+
+    if actions.just_pressed(Action::Confirm) {
+        // with nothing focused, confirm is left to whatever else uses it, such as name entry
+        let Some(target) = focus.0 else {
+            return;
+        };
+        if let Ok((_, _, _, ic, ..)) = buttons.get(target) {
+            sfx.send(PlaySfx(Sfx::Press));
+            ic.add_all(&mut commands);
+        }
+    } else if actions.just_pressed(Action::Back) && !typing {
+        if let Some((_, _, _, ic, ..)) = buttons
+            .iter()
+            .find(|(entity, .., is_back)| *is_back && visible.iter().any(|(v, _)| v == entity))
+        {
+            sfx.send(PlaySfx(Sfx::Press));
+            ic.add_all(&mut commands);
+        }
     }
 }
 
-/// Check Trigger components and schedule associated InteractionCommand if necessary
+/// check Trigger components, and schedule their UiCommands when their condition becomes met
 pub fn trigger_check(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    triggers: Query<(&Trigger, &UiCommands)>,
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    game: Option<Res<Game>>,
+    actions: Actions,
+    mut triggers: Query<(Entity, &mut Trigger, &UiCommands)>,
 ) {
-    fn loaded(ls: Option<LoadState>) -> bool {
-        ls == Some(LoadState::Loaded)
-    }
-    for (trigger, command) in triggers.iter() {
-        match trigger {
-            Trigger::AssetsLoaded(assets) => {
-                if assets
+    let load_state = |id| asset_server.get_load_state(id);
+    for (entity, mut trigger, command) in triggers.iter_mut() {
+        let trigger = &mut *trigger;
+        let met = match &mut trigger.condition {
+            TriggerCondition::AssetsLoaded(assets) => assets
+                .iter()
+                .all(|handle| load_state(handle.id()) == Some(LoadState::Loaded)),
+            TriggerCondition::ScenesLoaded(scenes) => scenes
+                .iter()
+                .all(|handle| load_state(handle.id().untyped()) == Some(LoadState::Loaded)),
+            TriggerCondition::AssetsFailed(assets) => {
+                let mut failed = assets
                     .iter()
-                    .map(|handle| asset_server.get_load_state(handle.id()))
-                    .all(loaded)
-                {
-                    command.add_all(&mut commands);
+                    .filter(|handle| load_state(handle.id()) == Some(LoadState::Failed))
+                    .peekable();
+                let any_failed = failed.peek().is_some();
+                if !trigger.met {
+                    for path in failed.filter_map(|handle| handle.path()) {
+                        warn!("could not load {path}");
+                    }
                 }
+                any_failed
             }
-            Trigger::ScenesLoaded(scenes) => {
-                if scenes
-                    .iter()
-                    .map(|handle| asset_server.get_load_state(handle.id()))
-                    .all(loaded)
-                {
-                    command.add_all(&mut commands);
-                }
+            // a frame long enough for a repeating timer to wrap more than once still fires it
+            TriggerCondition::Elapsed(timer) => {
+                timer.tick(time.delta()).times_finished_this_tick() > 0
+            }
+            TriggerCondition::StateEntered(wanted) => state.get() == wanted,
+            TriggerCondition::ScoreReached(score) => {
+                game.as_ref().is_some_and(|game| game.score >= *score)
+            }
+            TriggerCondition::ActionPressed(action) => actions.pressed(*action),
+        };
+        // each time a timer finishes is a new firing, even if it also finished last frame
+        let elapsed = matches!(trigger.condition, TriggerCondition::Elapsed(_));
+        if met && (!trigger.met || elapsed) {
+            // removed first, in case the commands despawn this entity
+            if !trigger.repeat {
+                commands.entity(entity).remove::<Trigger>();
             }
+            command.add_all(&mut commands);
         }
+        trigger.met = met;
     }
 }
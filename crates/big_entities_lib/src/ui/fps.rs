@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 use bevy::diagnostic::DiagnosticsStore;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use crate::actions::{Action, Actions};
+use crate::save::DataDir;
+use crate::settings::Settings;
 
 /// Marker to find the container entity so we can show/hide the FPS counter
 #[derive(Component)]
@@ -122,16 +125,14 @@ pub fn fps_text_update_system(
     }
 }
 
-/// Toggle the FPS counter when pressing F12
+/// Toggle the FPS counter setting when the ToggleFps action is pressed
 pub fn fps_counter_showhide(
-    mut q: Query<&mut Visibility, With<FpsRoot>>,
-    kbd: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    dir: Res<DataDir>,
+    actions: Actions,
 ) {
-    if kbd.just_pressed(KeyCode::F12) {
-        let mut vis = q.single_mut();
-        *vis = match *vis {
-            Visibility::Hidden => Visibility::Visible,
-            _ => Visibility::Hidden,
-        };
+    if actions.just_pressed(Action::ToggleFps) {
+        settings.show_fps = !settings.show_fps;
+        settings.save(&dir);
     }
 }
\ No newline at end of file
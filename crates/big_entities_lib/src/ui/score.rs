@@ -1,8 +1,113 @@
 use bevy::prelude::*;
 
+use crate::{
+    chunks::BoardPosition,
+    effects::StatusEffects,
+    pawn::{free_movement::FirstPersonPawn, Player},
+    pickup::Pickup,
+    states::Scope,
+    Game,
+};
+
 #[derive(Component)]
 pub enum DataDisplay {
     Score,
     PickupPosition,
     PlayerPosition,
+    /// the player's active status effects
+    Effects,
+}
+
+/// show the scoreboard when a game's player is spawned, unless it is already showing
+pub fn spawn_scoreboard(
+    mut commands: Commands,
+    players: Query<(), Added<Player>>,
+    displays: Query<(), With<DataDisplay>>,
+) {
+    if players.is_empty() || !displays.is_empty() {
+        return;
+    }
+    let rows = [
+        (DataDisplay::Score, "Score:", 5.0, Color::rgb(0.5, 0.5, 1.0)),
+        (
+            DataDisplay::PickupPosition,
+            "Cake:",
+            50.0,
+            Color::rgb(0.5, 0.5, 1.0),
+        ),
+        (
+            DataDisplay::PlayerPosition,
+            "Cake:",
+            90.0,
+            Color::rgb(0.5, 0.5, 1.0),
+        ),
+        (DataDisplay::Effects, "", 130.0, Color::rgb(1.0, 0.8, 0.3)),
+    ];
+    for (data, text, top, color) in rows {
+        commands.spawn((
+            Scope::Game,
+            data,
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 40.0,
+                    color,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(top),
+                left: Val::Px(5.0),
+                ..default()
+            }),
+        ));
+    }
+}
+
+/// update the text of the scoreboard
+pub fn update_scoreboard(
+    game: Res<Game>,
+    mut query: Query<(&mut Text, &DataDisplay)>,
+    pickups: Query<&BoardPosition, (With<Pickup>, Without<DataDisplay>, Without<Player>)>,
+    players: Query<
+        (
+            &BoardPosition,
+            Option<&FirstPersonPawn>,
+            Option<&StatusEffects>,
+        ),
+        (With<Player>, Without<DataDisplay>),
+    >,
+) {
+    for (mut text, data) in query.iter_mut() {
+        use DataDisplay as DD;
+        match data {
+            DD::Score => text.sections[0].value = format!("Score: {}", game.score),
+            DD::PickupPosition => {
+                let Some(bp) = pickups.iter().next() else {
+                    continue;
+                };
+                text.sections[0].value = format!("Cake: {},{}", bp.x, bp.y);
+            }
+            DD::PlayerPosition => {
+                let Some((bp, fpp, _)) = players.iter().next() else {
+                    continue;
+                };
+                text.sections[0].value = match fpp {
+                    Some(fpp) => format!("Player: {},{}, look {}", bp.x, bp.y, fpp.look_rotation),
+                    None => format!("Player: {},{}", bp.x, bp.y),
+                };
+            }
+            DD::Effects => {
+                let Some((_, _, Some(effects))) = players.iter().next() else {
+                    continue;
+                };
+                text.sections[0].value = effects
+                    .iter()
+                    .map(|e| format!("{} {:.1}s", e.kind.label(), e.remaining()))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+            }
+        }
+    }
 }
@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
+
+use super::{screen_bundle, spawn_button, spawn_heading, BackButton, UiCommand, UiCommands};
+use crate::{settings::Setting, states::GameState};
+
+/// Builds ui from `.ui.ron` screen files, and rebuilds it whenever a file changes
+pub struct UiScreenPlugin;
+
+impl Plugin for UiScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<UiScreen>::new(&["ui.ron"]))
+            .add_systems(Update, (build_screens, draw_progress_bars).chain());
+    }
+}
+
+/// A screen of ui, as described in a `.ui.ron` file
+#[derive(serde::Deserialize, bevy::asset::Asset, bevy::reflect::TypePath, Debug)]
+pub struct UiScreen {
+    /// shown in a centered column, from top to bottom
+    pub children: Vec<UiNode>,
+}
+
+/// One element of a ui screen
+#[derive(serde::Deserialize, Clone, Debug)]
+pub enum UiNode {
+    /// a group of elements, laid out in a column, or in a row
+    Panel {
+        #[serde(default)]
+        row: bool,
+        /// space between the elements, in pixels
+        #[serde(default = "default_gap")]
+        gap: f32,
+        children: Vec<UiNode>,
+    },
+    /// large title text
+    Heading(String),
+    Label {
+        text: String,
+        #[serde(default = "default_font_size")]
+        size: f32,
+    },
+    /// a bar that fills up as something makes progress, such as loading
+    ProgressBar {
+        #[serde(default = "default_bar_width")]
+        width: f32,
+    },
+    /// a button that runs its commands, in order, when pressed
+    Button {
+        label: String,
+        commands: Vec<UiAction>,
+        /// also pressed by the Back action
+        #[serde(default)]
+        back: bool,
+    },
+}
+
+fn default_gap() -> f32 {
+    10.0
+}
+
+fn default_font_size() -> f32 {
+    40.0
+}
+
+fn default_bar_width() -> f32 {
+    400.0
+}
+
+/// A UiCommand as written in a screen file, which refers to the screen instead of to entities
+#[derive(serde::Deserialize, Clone, Debug)]
+pub enum UiAction {
+    SwitchState(GameState),
+    /// despawn the screen this button is on
+    DespawnScreen,
+    CaptureMouse,
+    ReleaseMouse,
+    Terminate,
+    SaveGame,
+    /// start playing the saved game, despawning this screen
+    LoadGame,
+    WatchReplay,
+    CycleWorld,
+    EditSeed,
+    ChangeSetting(Setting, i32),
+    OpenUrl(String),
+    PlaySound(String),
+    /// show another screen file, by its asset path
+    SpawnScreen(String),
+    /// a command added by a plugin, by its name, and its argument
+    Custom(String, String),
+}
+
+impl UiAction {
+    /// the command this runs, for a button on the given screen
+    pub fn command(&self, screen: Entity) -> UiCommand {
+        match self {
+            UiAction::SwitchState(state) => UiCommand::SwitchState(*state),
+            UiAction::DespawnScreen => UiCommand::Despawn(screen),
+            UiAction::CaptureMouse => UiCommand::CaptureMouse,
+            UiAction::ReleaseMouse => UiCommand::ReleaseMouse,
+            UiAction::Terminate => UiCommand::Terminate,
+            UiAction::SaveGame => UiCommand::SaveGame,
+            UiAction::LoadGame => UiCommand::LoadGame(screen),
+            UiAction::WatchReplay => UiCommand::WatchReplay,
+            UiAction::CycleWorld => UiCommand::CycleWorld,
+            UiAction::EditSeed => UiCommand::EditSeed,
+            UiAction::ChangeSetting(setting, steps) => UiCommand::ChangeSetting(*setting, *steps),
+            UiAction::OpenUrl(url) => UiCommand::OpenUrl(url.clone()),
+            UiAction::PlaySound(path) => UiCommand::PlaySound(path.clone()),
+            UiAction::SpawnScreen(path) => UiCommand::SpawnScreen(path.clone()),
+            UiAction::Custom(name, argument) => UiCommand::Custom(name.clone(), argument.clone()),
+        }
+    }
+}
+
+/// How full a progress bar is, from 0.0 to 1.0
+#[derive(Component, Default, PartialEq, Debug)]
+pub struct ProgressBar(pub f32);
+
+/// The filled part of a progress bar
+#[derive(Component)]
+pub struct ProgressFill;
+
+/// Fills its entity with the ui of a screen file, once the file is loaded and again whenever it changes
+#[derive(Component)]
+pub struct UiScreenRoot {
+    pub screen: Handle<UiScreen>,
+    built: bool,
+}
+
+impl UiScreenRoot {
+    pub fn new(screen: Handle<UiScreen>) -> UiScreenRoot {
+        UiScreenRoot {
+            screen,
+            built: false,
+        }
+    }
+}
+
+/// show the screen file at this asset path, over the ui of the current state
+pub fn open_screen(world: &mut World, path: String) {
+    let state = *world.resource::<State<GameState>>().get();
+    let screen = world.resource::<AssetServer>().load(path);
+    world.spawn((screen_bundle(state), UiScreenRoot::new(screen)));
+}
+
+/// spawn the ui of screens that have loaded, and respawn the ui of screens whose file changed
+fn build_screens(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<UiScreen>>,
+    screens: Res<Assets<UiScreen>>,
+    mut roots: Query<(Entity, &mut UiScreenRoot)>,
+) {
+    let modified = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (entity, mut root) in roots.iter_mut() {
+        if root.built && !modified.contains(&root.screen.id()) {
+            continue;
+        }
+        let Some(screen) = screens.get(&root.screen) else {
+            continue;
+        };
+        root.built = true;
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for node in screen.children.iter() {
+                    spawn_node(parent, node, entity);
+                }
+            });
+    }
+}
+
+/// spawn one element of a screen, and everything inside it
+fn spawn_node(parent: &mut ChildBuilder, node: &UiNode, screen: Entity) {
+    match node {
+        UiNode::Panel { row, gap, children } => {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: if *row {
+                            FlexDirection::Row
+                        } else {
+                            FlexDirection::Column
+                        },
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        row_gap: Val::Px(*gap),
+                        column_gap: Val::Px(*gap),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for child in children.iter() {
+                        spawn_node(parent, child, screen);
+                    }
+                });
+        }
+        UiNode::Heading(text) => spawn_heading(parent, text),
+        UiNode::Label { text, size } => {
+            parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: *size,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        }
+        UiNode::ProgressBar { width } => {
+            parent
+                .spawn((
+                    ProgressBar::default(),
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(*width),
+                            height: Val::Px(30.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        ProgressFill,
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.5, 0.5, 1.0).into(),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+        UiNode::Button {
+            label,
+            commands,
+            back,
+        } => {
+            let mut ui_commands = UiCommands::builder();
+            for action in commands.iter() {
+                ui_commands.push(action.command(screen));
+            }
+            if *back {
+                spawn_button(parent, label, (ui_commands, BackButton), ());
+            } else {
+                spawn_button(parent, label, ui_commands, ());
+            }
+        }
+    }
+}
+
+/// size the filled part of progress bars to how full they are
+fn draw_progress_bars(
+    bars: Query<(&ProgressBar, &Children), Changed<ProgressBar>>,
+    mut fills: Query<&mut Style, With<ProgressFill>>,
+) {
+    for (ProgressBar(fraction), children) in bars.iter() {
+        let mut fills = fills.iter_many_mut(children);
+        while let Some(mut style) = fills.fetch_next() {
+            style.width = Val::Percent(fraction.clamp(0.0, 1.0) * 100.0);
+        }
+    }
+}
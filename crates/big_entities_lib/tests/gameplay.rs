@@ -0,0 +1,157 @@
+use bevy::{
+    input::ButtonState,
+    prelude::{Handle, KeyCode, Scene},
+};
+use big_entities_lib::{
+    chunks::{BoardPosition, WorldConfiguration},
+    headless::{HeadlessGame, STEP},
+    states::GameState,
+};
+
+/// a small flat world with a fixed seed, played on the board
+fn board_world() -> WorldConfiguration {
+    ron::from_str("(width: 16, height: 16, seed: Some(7), control_mode: Board)").unwrap()
+}
+
+#[test]
+fn walking_onto_a_pickup_eats_it() {
+    let mut game = HeadlessGame::new(board_world());
+    game.start();
+    assert_eq!(game.state(), GameState::Playing);
+    let start = game.player_position().unwrap();
+    // whichever way the pawn faces, the tile ahead has a pickup on it
+    for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let position = BoardPosition {
+            x: start.x.checked_add_signed(dx).unwrap(),
+            y: start.y.checked_add_signed(dy).unwrap(),
+        };
+        game.spawn_pickup(0, position).unwrap();
+    }
+    assert_eq!(game.game().cake_eaten, 0);
+
+    game.press(KeyCode::W);
+    let ate = game.advance_until(2.0, |game| game.game().cake_eaten > 0);
+    game.release(KeyCode::W);
+
+    assert!(ate, "the pawn never reached a pickup");
+    assert_ne!(game.player_position(), Some(start));
+    assert_eq!(game.game().cake_eaten, 1);
+    assert_eq!(game.game().score, 1);
+}
+
+#[test]
+fn pickups_that_expire_cost_points() {
+    let mut game = HeadlessGame::new(board_world());
+    game.start();
+    // the fallback cake lasts five seconds, and costs ten points when it expires
+    game.advance(4.5);
+    assert_eq!(game.game().score, 0);
+    game.advance(1.0);
+    assert_eq!(game.game().score, -10);
+}
+
+#[test]
+fn a_low_score_ends_the_game() {
+    let mut game = HeadlessGame::new(board_world());
+    game.start();
+    game.game_mut().score = -499;
+    game.advance(0.5);
+    assert_eq!(game.state(), GameState::Playing);
+
+    game.game_mut().score = -500;
+    game.advance(0.1);
+    assert_eq!(game.state(), GameState::GameOver);
+    assert_eq!(game.player_position(), None);
+}
+
+#[test]
+fn the_simulation_spawns_nothing_to_draw() {
+    let mut game = HeadlessGame::new(board_world());
+    game.start();
+    game.advance(0.5);
+    assert!(game.player_position().is_some());
+    let world = &mut game.app.world;
+    let models = world.query::<&Handle<Scene>>().iter(world).count();
+    assert_eq!(models, 0);
+}
+
+#[test]
+fn a_replay_plays_out_like_the_recorded_game() {
+    let mut game = HeadlessGame::new(board_world());
+    game.start();
+    // wander about, long enough for pickups to be eaten or expire
+    for key in [
+        KeyCode::W,
+        KeyCode::Left,
+        KeyCode::W,
+        KeyCode::W,
+        KeyCode::Right,
+    ] {
+        game.press(key);
+        game.advance(1.0);
+        game.release(key);
+        game.advance(0.5);
+    }
+    let end = game.player_position();
+    let (score, cake_eaten) = (game.game().score, game.game().cake_eaten);
+    game.set_state(GameState::MainMenu);
+    let replay = game
+        .last_replay()
+        .expect("the game was not recorded")
+        .clone();
+    assert_eq!((replay.score, replay.cake_eaten), (score, cake_eaten));
+
+    let mut watched = HeadlessGame::new(board_world());
+    watched.watch(replay.clone());
+    // the replay can be paused, and carries on as recorded when resumed
+    watched.advance(1.0);
+    watched.press(KeyCode::Escape);
+    watched.update();
+    watched.release(KeyCode::Escape);
+    watched.update();
+    assert_eq!(watched.state(), GameState::Menu);
+    watched.set_state(GameState::Playing);
+
+    let seconds = replay.ticks.len() as f32 * STEP;
+    assert!(watched.advance_until(seconds + 1.0, |game| game.replay_played_out()));
+    assert_eq!(watched.player_position(), end);
+    assert_eq!(watched.game().score, score);
+    assert_eq!(watched.game().cake_eaten, cake_eaten);
+    // once it has played, the replay goes back to the title screen instead of playing on
+    watched.update();
+    assert_eq!(watched.state(), GameState::MainMenu);
+    assert!(!watched.watching());
+    // watching a replay doesn't record over it
+    assert!(watched.last_replay().is_none());
+}
+
+#[test]
+fn play_does_not_depend_on_the_frame_rate() {
+    // when keys go down or up, in seconds of game time. each is a frame boundary at both
+    // frame rates, and falls well inside a tick, so both give the input to the same ticks
+    let script = [
+        (0.12, KeyCode::W, ButtonState::Pressed),
+        (1.08, KeyCode::W, ButtonState::Released),
+        (1.32, KeyCode::Left, ButtonState::Pressed),
+        (1.48, KeyCode::Left, ButtonState::Released),
+        (1.64, KeyCode::W, ButtonState::Pressed),
+        (2.92, KeyCode::W, ButtonState::Released),
+    ];
+    let play = |frame_time| {
+        let mut game = HeadlessGame::with_frame_time(board_world(), frame_time);
+        game.start();
+        for (at, key, state) in script {
+            game.advance_to(at);
+            match state {
+                ButtonState::Pressed => game.press(key),
+                ButtonState::Released => game.release(key),
+            }
+        }
+        game.advance_to(5.32);
+        let position = game.player_position();
+        (position, game.game().score, game.game().cake_eaten)
+    };
+    let slow = play(1.0 / 25.0);
+    assert!(slow.0.is_some());
+    assert_eq!(slow, play(1.0 / 100.0));
+}
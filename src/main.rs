@@ -16,8 +16,7 @@ fn main() {
         app.add_systems(Update, handle_browser_resize);
         app.insert_resource(AssetMetaCheck::Never);
     }
-    app.insert_resource(Msaa::Off)
-        .insert_resource(ClearColor(Color::rgb_u8(169, 231, 255))) // rgb(169, 231, 255);
+    app.insert_resource(ClearColor(Color::rgb_u8(169, 231, 255))) // rgb(169, 231, 255);
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Big Entities".to_string(),